@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use bevy_easings::EaseFunction;
 
 #[derive(Component)]
 pub struct MyCameraMarker;
@@ -9,8 +10,78 @@ pub struct OrbitCameraTarget {
     pub yaw: f32,
     pub pitch: f32,
     pub active: bool,
+    /// Radius of the sphere cast that keeps the camera out of walls.
+    pub probe_radius: f32,
+    /// The camera is never pulled in closer than this.
+    pub min_distance: f32,
+    /// How fast the camera moves back out once the way is clear, in units per second.
+    pub recovery_speed: f32,
+    /// Swing the camera around behind the character while the player leaves it alone.
+    pub auto_follow: bool,
+    /// How long after the camera was last moved by hand it starts following, in seconds.
+    pub auto_follow_delay: f32,
+    /// How quickly the camera closes in on where it's heading, as a fraction per second.
+    pub auto_follow_rate: f32,
+    /// The camera only follows while the character is faster than this.
+    pub auto_follow_min_speed: f32,
+    /// How far the camera tilts per radian of slope while following on the ground, rising on
+    /// the way down so more of the slope ahead is in view.
+    pub auto_follow_pitch_factor: f32,
+    /// Time since the camera was last moved by hand, in seconds.
+    pub time_since_look: f32,
+    /// How the field of view and distance respond to the character's speed.
+    pub speed_profile: CameraSpeedProfile,
 }
 
+/// Eases from one value at `CameraSpeedProfile::min_speed` to another at `max_speed`.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct SpeedCurve {
+    pub at_min_speed: f32,
+    pub at_max_speed: f32,
+    /// Shapes the curve. Above 1 the value stays near `at_min_speed` until the character is
+    /// going fast, and below 1 it changes early.
+    pub exponent: f32,
+}
+
+impl SpeedCurve {
+    /// A curve that doesn't change with speed.
+    pub fn constant(value: f32) -> Self {
+        Self {
+            at_min_speed: value,
+            at_max_speed: value,
+            exponent: 1.0,
+        }
+    }
+
+    /// `t` is how far from the minimum to the maximum speed the character is, from 0 to 1.
+    pub fn sample(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0).powf(self.exponent);
+        self.at_min_speed + (self.at_max_speed - self.at_min_speed) * t
+    }
+}
+
+/// Widens the field of view and pulls the camera back as the character speeds up.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct CameraSpeedProfile {
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Vertical field of view, in radians.
+    pub fov: SpeedCurve,
+    /// Multiplies `OrbitCameraTarget::distance`.
+    pub distance_scale: SpeedCurve,
+    /// How quickly the camera catches up with changes in speed, as a fraction per second.
+    pub smoothing: f32,
+}
+
+impl CameraSpeedProfile {
+    /// Where `speed` falls between the minimum and maximum speeds, from 0 to 1.
+    pub fn speed_fraction(&self, speed: f32) -> f32 {
+        ((speed - self.min_speed) / (self.max_speed - self.min_speed).max(f32::EPSILON))
+            .clamp(0.0, 1.0)
+    }
+}
+
+/// Which way is forward for input, for characters that no `PlayerCamera` follows.
 #[derive(Component, Reflect)]
 pub struct ViewpointMappable {
     pub forward: Quat,
@@ -19,4 +90,125 @@ pub struct ViewpointMappable {
 #[derive(Component, Reflect)]
 pub struct ViewpointMappedInput {
     pub move_input: Vec2,
+    /// Taken when the stick leaves neutral, and dropped when it goes back. It follows the camera
+    /// until the surface under the character turns far enough away from where it was then, and
+    /// after that it's kept so running through a loop or up a wall doesn't change which way
+    /// forward is.
+    pub control_lock: Option<ControlLock>,
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct ControlLock {
+    /// The surface normal when the stick left neutral.
+    pub surface_up: Vec3,
+    /// Maps the stick into the surface's 2D frame: the first column is stick right, and the
+    /// second is stick up.
+    pub stick_basis: Mat2,
+}
+
+/// A camera that follows one character's `OrbitCameraTarget`, in its own part of the window.
+#[derive(Component, Reflect)]
+pub struct PlayerCamera {
+    pub target: Entity,
+    /// How far from the target the camera currently is, after being pulled in by walls.
+    pub distance: f32,
+    /// The target's speed, smoothed by its `CameraSpeedProfile`.
+    pub speed: f32,
+    /// The `CameraZone` the target is in, if any.
+    pub zone: Option<Entity>,
+    #[reflect(ignore)]
+    pub transition: Option<CameraTransition>,
+}
+
+/// A blend from where the camera was when the target entered or left a zone.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraTransition {
+    pub from: Transform,
+    pub elapsed: f32,
+    pub duration: f32,
+    pub easing: EaseFunction,
+}
+
+/// A level placed volume that takes over the camera of any player inside it, for sections like
+/// loops and side views that need an authored camera. It needs a sensor `Collider` on the
+/// `MyCollisionLayers::CameraZone` layer.
+#[derive(Component, Reflect)]
+#[reflect(from_reflect = false)]
+pub struct CameraZone {
+    pub mode: CameraZoneMode,
+    /// When zones overlap, the highest priority one wins.
+    pub priority: i32,
+    /// How long the camera takes to blend into this zone, and back out of it, in seconds.
+    pub blend_time: f32,
+    #[reflect(ignore)]
+    pub easing: EaseFunction,
+}
+
+#[derive(Reflect, Clone, Debug)]
+pub enum CameraZoneMode {
+    /// Orbit the player from a fixed angle, like `OrbitCameraTarget` with the stick locked.
+    FixedAngle { yaw: f32, pitch: f32, distance: f32 },
+    /// Stay `distance` behind the player on the side away from `point`, raised by `height`,
+    /// and look at `point`.
+    LookAt {
+        point: Vec3,
+        distance: f32,
+        height: f32,
+    },
+    /// Ride the rail to the point on it nearest the player, and look at the player.
+    Rail(CameraRail),
+}
+
+/// A Catmull-Rom spline through `points`, in world space.
+#[derive(Reflect, Clone, Debug, Default)]
+pub struct CameraRail {
+    pub points: Vec<Vec3>,
+}
+
+impl CameraRail {
+    /// How many samples each segment is searched with, in `closest_point`.
+    const SAMPLES_PER_SEGMENT: usize = 16;
+
+    /// The point `t` of the way along segment `segment`, which runs from `points[segment]` to
+    /// `points[segment + 1]`.
+    pub fn sample(&self, segment: usize, t: f32) -> Vec3 {
+        let last = self.points.len() - 1;
+        let point = |i: isize| self.points[i.clamp(0, last as isize) as usize];
+        let i = segment as isize;
+        let (p0, p1, p2, p3) = (point(i - 1), point(i), point(i + 1), point(i + 2));
+        let (t2, t3) = (t * t, t * t * t);
+        0.5 * ((2.0 * p1)
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+    }
+
+    /// The point on the rail nearest `target`, or `None` if the rail has no points.
+    pub fn closest_point(&self, target: Vec3) -> Option<Vec3> {
+        match self.points.len() {
+            0 => None,
+            1 => Some(self.points[0]),
+            len => (0..len - 1)
+                .flat_map(|segment| {
+                    (0..=Self::SAMPLES_PER_SEGMENT).map(move |step| {
+                        self.sample(segment, step as f32 / Self::SAMPLES_PER_SEGMENT as f32)
+                    })
+                })
+                .min_by(|a, b| {
+                    a.distance_squared(target)
+                        .total_cmp(&b.distance_squared(target))
+                }),
+        }
+    }
+}
+
+/// Which way the window is first split between two players' cameras.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum SplitScreen {
+    /// One player above the other.
+    #[default]
+    Horizontal,
+    /// Players side by side.
+    Vertical,
 }
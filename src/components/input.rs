@@ -0,0 +1,435 @@
+use std::{fs, io, path::Path};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+/// Something the player can do, independent of which device does it.
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Move,
+    Look,
+    Jump,
+    Crouch,
+    Boost,
+    CameraReset,
+}
+
+impl Action {
+    /// Whether the action is driven like a stick, rather than like a button.
+    pub fn is_axes(&self) -> bool {
+        matches!(self, Action::Move | Action::Look)
+    }
+}
+
+/// Whether a binding is on the keyboard and mouse, or a gamepad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BindingDevice {
+    KeyboardMouse,
+    Gamepad,
+}
+
+/// A physical input that an action can be bound to. Buttons drive button actions, and the stick
+/// shaped bindings drive `Move` and `Look`.
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Binding {
+    Key(KeyCode),
+    MouseButton(MouseButton),
+    GamepadButton(GamepadButtonType),
+    /// Four keys acting as a stick.
+    KeyAxes {
+        up: KeyCode,
+        down: KeyCode,
+        left: KeyCode,
+        right: KeyCode,
+    },
+    GamepadStick {
+        x: GamepadAxisType,
+        y: GamepadAxisType,
+    },
+    /// Mouse movement while mouse look is on, scaled by `InputBindings::mouse_sensitivity`.
+    MouseMotion,
+}
+
+impl Binding {
+    pub fn device(&self) -> BindingDevice {
+        match self {
+            Binding::Key(_)
+            | Binding::MouseButton(_)
+            | Binding::KeyAxes { .. }
+            | Binding::MouseMotion => BindingDevice::KeyboardMouse,
+            Binding::GamepadButton(_) | Binding::GamepadStick { .. } => BindingDevice::Gamepad,
+        }
+    }
+
+    /// Whether the binding is stick shaped, so it can drive `Move` and `Look`.
+    pub fn is_axes(&self) -> bool {
+        matches!(
+            self,
+            Binding::KeyAxes { .. } | Binding::GamepadStick { .. } | Binding::MouseMotion
+        )
+    }
+
+    /// The first key or button pressed this frame, for capturing a new binding.
+    pub fn just_pressed(sources: &InputSources) -> Option<Binding> {
+        if sources.keyboard_mouse {
+            if let Some(key) = sources.keys.get_just_pressed().next() {
+                return Some(Binding::Key(*key));
+            }
+            if let Some(button) = sources.mouse_buttons.get_just_pressed().next() {
+                return Some(Binding::MouseButton(*button));
+            }
+        }
+        sources
+            .gamepad_buttons
+            .get_just_pressed()
+            .find(|button| sources.gamepads.contains(&button.gamepad))
+            .map(|button| Binding::GamepadButton(button.button_type))
+    }
+
+    /// Whether `sources` includes the device this binding is on.
+    fn available(&self, sources: &InputSources) -> bool {
+        match self.device() {
+            BindingDevice::KeyboardMouse => sources.keyboard_mouse,
+            BindingDevice::Gamepad => true,
+        }
+    }
+
+    fn button(&self, sources: &InputSources) -> Option<ActionButton> {
+        if !self.available(sources) {
+            return None;
+        }
+        match *self {
+            Binding::Key(key) => Some(ActionButton::from_input(sources.keys, [key])),
+            Binding::MouseButton(button) => {
+                Some(ActionButton::from_input(sources.mouse_buttons, [button]))
+            }
+            Binding::GamepadButton(button_type) => Some(ActionButton::from_input(
+                sources.gamepad_buttons,
+                sources
+                    .gamepads
+                    .iter()
+                    .map(|gamepad| GamepadButton::new(*gamepad, button_type)),
+            )),
+            _ => None,
+        }
+    }
+
+    fn axes(&self, sources: &InputSources, bindings: &InputBindings) -> Option<Vec2> {
+        if !self.available(sources) {
+            return None;
+        }
+        match *self {
+            Binding::KeyAxes {
+                up,
+                down,
+                left,
+                right,
+            } => {
+                let key = |key| if sources.keys.pressed(key) { 1.0 } else { 0.0 };
+                Some(Vec2::new(key(right) - key(left), key(up) - key(down)))
+            }
+            Binding::GamepadStick { x, y } => Some(
+                sources
+                    .gamepads
+                    .iter()
+                    .map(|gamepad| {
+                        let axis = |axis_type| {
+                            sources
+                                .gamepad_axes
+                                .get(GamepadAxis::new(*gamepad, axis_type))
+                                .unwrap_or(0.0)
+                        };
+                        Vec2::new(axis(x), axis(y))
+                    })
+                    .sum(),
+            ),
+            // The cursor is only held for looking while mouse look is on; otherwise it's
+            // pointing at things.
+            Binding::MouseMotion if !sources.mouse_look => None,
+            Binding::MouseMotion => {
+                // Mouse y grows downward, stick y grows upward.
+                let invert = if bindings.invert_mouse_y { -1.0 } else { 1.0 };
+                let motion = Vec2::new(sources.mouse_motion.x, -sources.mouse_motion.y * invert);
+                Some(motion * bindings.mouse_sensitivity)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The device state that bindings are resolved against. It only borrows the input resources, so
+/// tests can resolve bindings against synthetic input.
+pub struct InputSources<'a> {
+    /// Whether to read the keyboard and mouse.
+    pub keyboard_mouse: bool,
+    pub keys: &'a Input<KeyCode>,
+    pub mouse_buttons: &'a Input<MouseButton>,
+    /// How fast the mouse is moving, in pixels per second.
+    pub mouse_motion: Vec2,
+    /// Whether mouse movement turns the camera, from [`MouseLook`].
+    pub mouse_look: bool,
+    pub gamepad_buttons: &'a Input<GamepadButton>,
+    pub gamepad_axes: &'a Axis<GamepadAxis>,
+    /// The gamepads to read.
+    pub gamepads: &'a [Gamepad],
+}
+
+/// Which bindings drive each action. Saved and loaded as RON, and can be changed at runtime.
+/// Settings missing from a saved file keep their defaults.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct InputBindings {
+    pub actions: HashMap<Action, Vec<Binding>>,
+    /// `Move` below this length is ignored, and above it is pushed to full length.
+    pub move_deadzone: f32,
+    /// Each axis of `Look` below this is ignored, except for mouse movement.
+    pub look_deadzone: f32,
+    /// Scales mouse movement, in pixels per second, into the same units as a stick.
+    pub mouse_sensitivity: f32,
+    /// Moving the mouse up looks down.
+    pub invert_mouse_y: bool,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let actions = [
+            (
+                Action::Move,
+                vec![
+                    Binding::KeyAxes {
+                        up: KeyCode::Up,
+                        down: KeyCode::Down,
+                        left: KeyCode::Left,
+                        right: KeyCode::Right,
+                    },
+                    Binding::GamepadStick {
+                        x: GamepadAxisType::LeftStickX,
+                        y: GamepadAxisType::LeftStickY,
+                    },
+                ],
+            ),
+            (
+                Action::Look,
+                vec![
+                    Binding::MouseMotion,
+                    Binding::GamepadStick {
+                        x: GamepadAxisType::RightStickX,
+                        y: GamepadAxisType::RightStickY,
+                    },
+                ],
+            ),
+            (
+                Action::Jump,
+                vec![
+                    Binding::Key(KeyCode::Space),
+                    Binding::GamepadButton(GamepadButtonType::South),
+                ],
+            ),
+            (
+                Action::Crouch,
+                vec![
+                    Binding::Key(KeyCode::ShiftLeft),
+                    Binding::GamepadButton(GamepadButtonType::East),
+                ],
+            ),
+            (
+                Action::Boost,
+                vec![
+                    Binding::Key(KeyCode::ControlLeft),
+                    Binding::GamepadButton(GamepadButtonType::West),
+                ],
+            ),
+            (
+                Action::CameraReset,
+                vec![
+                    Binding::Key(KeyCode::R),
+                    Binding::GamepadButton(GamepadButtonType::RightThumb),
+                ],
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        Self {
+            actions,
+            move_deadzone: 0.3,
+            look_deadzone: 0.1,
+            mouse_sensitivity: 0.005,
+            invert_mouse_y: false,
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds `binding` to `action`, replacing what was bound to it on the same kind of device, so
+    /// rebinding the keyboard jump leaves the gamepad jump alone. Returns false and changes
+    /// nothing if the binding can't drive the action: a button can't replace the stick of `Move`
+    /// or `Look`, and a stick can't be a button.
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> bool {
+        if action.is_axes() != binding.is_axes() {
+            return false;
+        }
+        let bindings = self.actions.entry(action).or_default();
+        bindings.retain(|b| b.device() != binding.device());
+        bindings.push(binding);
+        true
+    }
+
+    /// Works out what every action is doing from the current device state.
+    pub fn resolve(&self, sources: &InputSources) -> ActionState {
+        let move_input = self.axes(Action::Move, sources);
+        let move_input = if move_input.length() > self.move_deadzone {
+            move_input.normalize()
+        } else {
+            Vec2::ZERO
+        };
+
+        // The deadzone hides stick drift, which the mouse doesn't have.
+        let (mouse_look, stick_look): (Vec<Binding>, Vec<Binding>) = self
+            .bindings(Action::Look)
+            .iter()
+            .partition(|binding| matches!(binding, Binding::MouseMotion));
+        let stick_look = self.sum_axes(&stick_look, sources);
+        let look = Vec2::select(
+            stick_look.abs().cmpgt(Vec2::splat(self.look_deadzone)),
+            stick_look,
+            Vec2::ZERO,
+        ) + self.sum_axes(&mouse_look, sources);
+
+        ActionState {
+            move_input,
+            look,
+            jump: self.button(Action::Jump, sources),
+            crouch: self.button(Action::Crouch, sources),
+            boost: self.button(Action::Boost, sources),
+            camera_reset: self.button(Action::CameraReset, sources),
+        }
+    }
+
+    fn axes(&self, action: Action, sources: &InputSources) -> Vec2 {
+        self.sum_axes(self.bindings(action), sources)
+    }
+
+    fn sum_axes(&self, bindings: &[Binding], sources: &InputSources) -> Vec2 {
+        bindings
+            .iter()
+            .filter_map(|binding| binding.axes(sources, self))
+            .sum()
+    }
+
+    fn button(&self, action: Action, sources: &InputSources) -> ActionButton {
+        self.bindings(action)
+            .iter()
+            .filter_map(|binding| binding.button(sources))
+            .fold(ActionButton::default(), ActionButton::combine)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(ron)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let ron = self
+            .to_ron()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, ron)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_ron(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Whether mouse movement turns the camera of keyboard and mouse players, through
+/// `Binding::MouseMotion`. The demo turns this on while the cursor is grabbed.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct MouseLook {
+    pub active: bool,
+}
+
+/// The devices that drive a character and its camera, so several players can share a world.
+/// Characters without one read every device.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlayerSlot {
+    pub keyboard_mouse: bool,
+    pub gamepad: Option<Gamepad>,
+    /// Counts up as players join, so split screen keeps players in the order they joined.
+    pub join_order: u32,
+}
+
+impl PlayerSlot {
+    pub fn keyboard_mouse() -> Self {
+        Self {
+            keyboard_mouse: true,
+            gamepad: None,
+            join_order: 0,
+        }
+    }
+
+    pub fn gamepad(gamepad: Gamepad) -> Self {
+        Self {
+            keyboard_mouse: false,
+            gamepad: Some(gamepad),
+            join_order: 0,
+        }
+    }
+}
+
+/// The state of a button action this frame.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActionButton {
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+}
+
+impl ActionButton {
+    fn from_input<T: Copy + Eq + std::hash::Hash + Send + Sync + 'static>(
+        input: &Input<T>,
+        buttons: impl IntoIterator<Item = T>,
+    ) -> Self {
+        buttons
+            .into_iter()
+            .map(|button| ActionButton {
+                pressed: input.pressed(button),
+                just_pressed: input.just_pressed(button),
+                just_released: input.just_released(button),
+            })
+            .fold(ActionButton::default(), ActionButton::combine)
+    }
+
+    /// Several bindings on one action: it's held while any of them is, and only released when
+    /// none of them are.
+    fn combine(self, other: ActionButton) -> ActionButton {
+        let pressed = self.pressed || other.pressed;
+        ActionButton {
+            pressed,
+            just_pressed: self.just_pressed || other.just_pressed,
+            just_released: (self.just_released || other.just_released) && !pressed,
+        }
+    }
+}
+
+/// What a character's player is doing this frame, after resolving their bindings.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActionState {
+    /// Movement relative to the camera, with +y away from it.
+    pub move_input: Vec2,
+    /// Camera movement, in stick units.
+    pub look: Vec2,
+    pub jump: ActionButton,
+    pub crouch: ActionButton,
+    pub boost: ActionButton,
+    pub camera_reset: ActionButton,
+}
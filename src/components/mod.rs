@@ -1,2 +1,3 @@
 pub mod camera;
+pub mod input;
 pub mod player;
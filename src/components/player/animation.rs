@@ -1,9 +1,202 @@
-use bevy::prelude::*;
+use std::io;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    gltf::Gltf,
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
 
 #[derive(Component, Reflect)]
 pub struct Animated {
-    pub current_animation: usize,
+    /// Name of the state in the character's `AnimationStateMachine`.
+    pub state: String,
+    /// How long the character has been in `state`, in seconds.
+    pub time_in_state: f32,
+    pub speed: f32,
+}
+
+/// Picks which glTF animation a character plays from what the character is doing. Loaded from
+/// `.anim.ron` files.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct AnimationStateMachine {
+    /// Path of the glTF file the clips are in.
+    pub gltf: String,
+    #[serde(skip)]
+    #[dependency]
+    pub gltf_handle: Handle<Gltf>,
+    pub initial_state: String,
+    /// Checked before the current state's own transitions, from every state.
+    #[serde(default)]
+    pub any_state: Vec<AnimationTransition>,
+    pub states: Vec<AnimationState>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnimationState {
+    pub name: String,
+    /// Name of the glTF animation to play.
+    pub clip: String,
+    /// How long to blend into this state's clip, in seconds.
+    pub blend_time: f32,
+    pub speed: AnimationSpeed,
+    #[serde(default = "default_repeat")]
+    pub repeat: bool,
+    /// Checked in order; the first one whose conditions all hold is taken.
+    #[serde(default)]
+    pub transitions: Vec<AnimationTransition>,
+}
+
+fn default_repeat() -> bool {
+    true
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnimationTransition {
+    pub to: String,
+    pub when: Vec<AnimationCondition>,
+}
+
+/// Something about the character that a transition can depend on.
+#[derive(Deserialize, Debug, Clone)]
+pub enum AnimationCondition {
+    Grounded,
+    Airborne,
+    /// Moving up, along the character's up.
+    Rising,
+    Falling,
+    Rolling,
+    ChargingDash,
+    Skidding,
+    SpeedAbove(f32),
+    SpeedBelow(f32),
+    TimeInStateAbove(f32),
+    Not(Box<AnimationCondition>),
+}
+
+/// How fast a state's clip plays.
+#[derive(Deserialize, Debug, Clone)]
+pub enum AnimationSpeed {
+    Fixed(f32),
+    /// `scale` times the character's speed as a fraction of its top speed, and at least `min`.
+    Speed {
+        scale: f32,
+        min: f32,
+    },
+    /// `scale` times how fast the character is moving along its up, either way.
+    VerticalSpeed {
+        scale: f32,
+    },
+    /// `base`, plus `scale` times how full the dash charge is.
+    DashCharge {
+        base: f32,
+        scale: f32,
+    },
+}
+
+/// What the character is doing this frame, for the state machine to check conditions against.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AnimationContext {
+    pub grounded: bool,
+    pub rolling: bool,
+    pub skidding: bool,
+    /// `Some` with the charge, from 0 to 1, while charging a dash.
+    pub dash_charge: Option<f32>,
     pub speed: f32,
+    pub top_speed: f32,
+    /// Speed along the character's up.
+    pub vertical_speed: f32,
+    pub time_in_state: f32,
+}
+
+impl AnimationCondition {
+    pub fn holds(&self, context: &AnimationContext) -> bool {
+        match self {
+            AnimationCondition::Grounded => context.grounded,
+            AnimationCondition::Airborne => !context.grounded,
+            AnimationCondition::Rising => !context.grounded && context.vertical_speed > 0.0,
+            AnimationCondition::Falling => !context.grounded && context.vertical_speed <= 0.0,
+            AnimationCondition::Rolling => context.rolling,
+            AnimationCondition::ChargingDash => context.dash_charge.is_some(),
+            AnimationCondition::Skidding => context.skidding,
+            AnimationCondition::SpeedAbove(speed) => context.speed > *speed,
+            AnimationCondition::SpeedBelow(speed) => context.speed < *speed,
+            AnimationCondition::TimeInStateAbove(time) => context.time_in_state > *time,
+            AnimationCondition::Not(condition) => !condition.holds(context),
+        }
+    }
+}
+
+impl AnimationTransition {
+    pub fn holds(&self, context: &AnimationContext) -> bool {
+        self.when.iter().all(|condition| condition.holds(context))
+    }
+}
+
+impl AnimationSpeed {
+    pub fn evaluate(&self, context: &AnimationContext) -> f32 {
+        match *self {
+            AnimationSpeed::Fixed(speed) => speed,
+            AnimationSpeed::Speed { scale, min } => {
+                (context.speed / context.top_speed.max(f32::EPSILON) * scale).max(min)
+            }
+            AnimationSpeed::VerticalSpeed { scale } => context.vertical_speed.abs() * scale,
+            AnimationSpeed::DashCharge { base, scale } => {
+                base + context.dash_charge.unwrap_or(0.0) * scale
+            }
+        }
+    }
 }
 
-//pub fn load_anims(asset_server: &mut AssetServer, vec![(dh)])
+impl AnimationStateMachine {
+    pub fn from_ron(ron: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(ron)
+    }
+
+    pub fn state(&self, name: &str) -> Option<&AnimationState> {
+        self.states.iter().find(|state| state.name == name)
+    }
+
+    /// The state to move to from `current`, if any transition out of it holds.
+    pub fn next_state(&self, current: &str, context: &AnimationContext) -> Option<&str> {
+        let own = self
+            .state(current)
+            .map_or(&[][..], |state| state.transitions.as_slice());
+        self.any_state
+            .iter()
+            .chain(own)
+            .filter(|transition| transition.to != current)
+            .find(|transition| transition.holds(context))
+            .map(|transition| transition.to.as_str())
+    }
+}
+
+#[derive(Default)]
+pub struct AnimationStateMachineLoader;
+
+impl AssetLoader for AnimationStateMachineLoader {
+    type Asset = AnimationStateMachine;
+    type Settings = ();
+    type Error = io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<AnimationStateMachine, io::Error>> {
+        Box::pin(async move {
+            let mut ron = String::new();
+            reader.read_to_string(&mut ron).await?;
+            let mut machine = AnimationStateMachine::from_ron(&ron)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            machine.gltf_handle = load_context.load(machine.gltf.clone());
+            Ok(machine)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
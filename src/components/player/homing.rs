@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+/// Marks an entity the homing attack can lock on to. It also needs a collider on
+/// `MyCollisionLayers::Enemy`, which is how targets are found.
+#[derive(Component, Reflect, Default)]
+pub struct HomingTargetable;
+
+/// A character's homing attack: what it's locked on to, and whether it's dashing at it.
+#[derive(Component, Reflect, Default)]
+pub struct HomingAttack {
+    /// The target pressing jump in midair would dash to.
+    pub target: Option<Entity>,
+    /// The target the character is dashing to.
+    pub dashing_to: Option<Entity>,
+    /// How long the current dash has been going, in seconds.
+    pub dash_time: f32,
+}
+
+/// Sent when a character's homing target changes, or it hits one, so UI and audio can react.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HomingTargetEvent {
+    Acquired { character: Entity, target: Entity },
+    Lost { character: Entity, target: Entity },
+    Hit { character: Entity, target: Entity },
+}
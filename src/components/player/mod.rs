@@ -1,3 +1,5 @@
 pub mod animation;
+pub mod homing;
 pub mod physics;
+pub mod replay;
 pub mod sensors;
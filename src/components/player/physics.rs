@@ -5,17 +5,75 @@ pub struct PlatformingCharacterControl {
     pub move_input: Vec2,
     pub facing_2d: Vec2,
     pub jump_pressed: bool,
+    /// Set when jump is pressed, and cleared once a fixed tick has seen it, so taps shorter than
+    /// a tick aren't lost.
+    pub jump_press_latched: bool,
+    pub crouch_pressed: bool,
 }
 
+/// Tuning for a character. Speeds are in units per second, and accelerations in units per second
+/// squared, so they don't depend on the fixed timestep.
 #[derive(Component, Reflect)]
 pub struct PlatformingCharacterValues {
     pub acceleration_speed: f32,
     pub air_acceleration_speed: f32,
+    /// Extra acceleration applied when input opposes the current ground speed.
     pub deceleration_speed: f32,
     pub top_speed: f32,
     pub friction_speed: f32,
-    pub gravity: f32,
+    /// Acceleration from gravity. It can point any way: slopes, jumps, falls, and the plane the
+    /// character steers across in the air all follow it.
+    pub gravity: Vec3,
+    /// How much of its velocity the character loses per second in the air.
+    pub air_drag: f32,
+    /// The character never moves faster than this in the air.
+    pub air_top_speed: f32,
+    /// Upward speed a jump starts with.
     pub jump_speed: f32,
+    /// Releasing jump while rising faster than this cuts the upward speed down to it.
+    pub jump_release_speed: f32,
+    /// How long after running off a ledge a jump still counts, in seconds.
+    pub coyote_time: f32,
+    /// How long before landing a jump press is remembered for, in seconds.
+    pub jump_buffer_time: f32,
+    /// How strongly gravity speeds the character up or slows it down along a slope while running.
+    pub slope_factor: f32,
+    /// Slope factor while rolling uphill.
+    pub roll_uphill_slope_factor: f32,
+    /// Slope factor while rolling downhill.
+    pub roll_downhill_slope_factor: f32,
+    /// Below this ground speed, the character falls off surfaces steeper than 45 degrees.
+    pub min_stick_speed: f32,
+    /// Grip that keeps the character on a surface, on top of what its speed around a curve
+    /// provides. Compared against how hard gravity pulls the character off the surface.
+    pub surface_adhesion: f32,
+    /// Pressing crouch above this ground speed starts rolling.
+    pub roll_start_speed: f32,
+    /// Rolling stops when ground speed drops below this.
+    pub roll_stop_speed: f32,
+    pub roll_friction_speed: f32,
+    /// Deceleration from pushing against the direction of a roll. Input can't speed up a roll.
+    pub roll_deceleration_speed: f32,
+    /// Collider radius while rolling, so the character fits through smaller gaps.
+    pub rolling_radius: f32,
+    /// How far away the homing attack can lock on to targets.
+    pub homing_range: f32,
+    /// Half angle, in radians, of the cone in front of the character that targets must be in.
+    pub homing_cone_angle: f32,
+    pub homing_speed: f32,
+    /// Gives up on a homing dash that hasn't reached its target after this many seconds.
+    pub homing_max_dash_time: f32,
+    /// Upward speed the character bounces off a target with.
+    pub homing_bounce_speed: f32,
+    /// Charge added by each jump press while charging a dash.
+    pub charge_dash_rev: f32,
+    pub charge_dash_max_charge: f32,
+    /// How quickly the charge drains away, as a fraction per second.
+    pub charge_dash_decay: f32,
+    /// Launch speed of a dash with no charge.
+    pub charge_dash_base_speed: f32,
+    /// Launch speed added for each unit of charge.
+    pub charge_dash_speed_per_charge: f32,
     /// Radius for slope detection, and the amount of distance we want to have from the ground. it's a 'cushion' around the actual collider.
     pub cushion_radius: f32,
     /// How big our 'footprint' is.
@@ -26,6 +84,39 @@ pub struct PlatformingCharacterValues {
     pub slope_cast_distance: f32,
 }
 
+impl PlatformingCharacterValues {
+    /// Straight up, away from gravity.
+    pub fn up(&self) -> Vec3 {
+        (self.gravity * -1.0).try_normalize().unwrap_or(Vec3::Y)
+    }
+
+    /// Rotation from flat ground to the plane square to gravity, which the character steers
+    /// across and looks for the ground below while in the air.
+    pub fn air_rotation(&self) -> Quat {
+        Quat::from_rotation_arc(Vec3::Y, self.up())
+    }
+
+    pub fn slope_factor(&self, rolling: bool, uphill: bool) -> f32 {
+        match (rolling, uphill) {
+            (false, _) => self.slope_factor,
+            (true, true) => self.roll_uphill_slope_factor,
+            (true, false) => self.roll_downhill_slope_factor,
+        }
+    }
+
+    /// The cushion and obstacle detection radii. While rolling the obstacle radius shrinks to
+    /// `rolling_radius`, and the cushion shrinks by as much so the bottom of the ball stays at
+    /// the same height.
+    pub fn body_radii(&self, rolling: bool) -> (f32, f32) {
+        if rolling {
+            let shrink = self.obstacle_detection_radius - self.rolling_radius;
+            (self.cushion_radius - shrink, self.rolling_radius)
+        } else {
+            (self.cushion_radius, self.obstacle_detection_radius)
+        }
+    }
+}
+
 #[derive(Component, Reflect)]
 pub struct PlatformingCharacterPhysics {
     pub ground_speed: Vec2,
@@ -34,20 +125,97 @@ pub struct PlatformingCharacterPhysics {
     /// direction the ground is in
     pub ground_cast_direction: Vec3,
     pub air_speed: AirSpeed,
-    pub wall_running: bool,
     /// feedback from 3d collisions to use for damping ground speed
     pub wall_collision_normal: Option<Vec3>,
     pub overall_rotation: Quat,
     pub show_gizmos: bool,
-    /// When ceiling running, this quaternion represents the rotation from ground to ceiling that passes through the wall that was climbed.
-    pub ceiling_run_quat: Option<Quat>,
+    /// Rotation from flat ground to the surface we're running on. Rotating `Vec3::NEG_Y` by this
+    /// gives `ground_cast_direction`. It's updated by small steps as the surface normal changes,
+    /// so it stays well defined on walls and ceilings.
+    pub surface_rotation: Quat,
+    /// The direction of travel along the surface from the last kinematic step, in world space.
+    pub slope_direction: Vec3,
+    /// Whether the character is rolling, which changes how slopes affect it.
+    pub rolling: bool,
+    /// Whether the character is in the air because it jumped, so letting go of jump can still cut
+    /// the jump short.
+    pub jumping: bool,
+    /// Time left in which the character can still jump after running off a ledge.
+    pub coyote_time_left: f32,
+    pub charge_dash: ChargeDash,
+}
+
+impl PlatformingCharacterPhysics {
+    /// Switches to the in-air state, carrying over `velocity`.
+    pub fn leave_ground(&mut self, velocity: Vec3, values: &PlatformingCharacterValues) {
+        // While we're in the air, the air speed is the whole velocity, and ground speed is unused
+        // until we land.
+        self.air_speed = AirSpeed::InAir(velocity);
+        self.jumping = false;
+        self.coyote_time_left = 0.0;
+        self.ground_speed = Vec2::ZERO;
+        self.fall_with_gravity(values);
+    }
+
+    /// Lines the surface frame up with gravity, for while the character is in the air.
+    pub fn fall_with_gravity(&mut self, values: &PlatformingCharacterValues) {
+        self.surface_rotation = values.air_rotation();
+        self.ground_cast_direction = values.up() * -1.0;
+    }
+
+    /// How fast the character is going, along the ground or through the air.
+    pub fn speed(&self) -> f32 {
+        match self.air_speed {
+            AirSpeed::Grounded { .. } => self.ground_speed.length(),
+            AirSpeed::InAir(velocity) => velocity.length(),
+        }
+    }
+
+    /// How fast the character is moving across the surface, from the ground speed while grounded
+    /// or the part of the velocity square to gravity while in the air. Either way it's in the
+    /// frame of `surface_rotation`.
+    pub fn planar_velocity(&self) -> Vec2 {
+        match self.air_speed {
+            AirSpeed::Grounded { .. } => self.ground_speed,
+            AirSpeed::InAir(velocity) => self.surface_rotation.inverse().mul_vec3(velocity).xz(),
+        }
+    }
 }
 
 #[derive(Component, Reflect)]
 pub struct PlatformingCharacterPhysicsAccel {
     pub ground_acceleration: Vec2,
     pub ground_friction: f32,
-    pub air_acceleration: f32,
+    /// Jump off the ground this tick.
+    pub jump: bool,
+    /// Time left before a buffered jump press is forgotten.
+    pub jump_buffer_left: f32,
+    /// `PlatformingCharacterControl::jump_pressed` from the previous tick, to find new presses.
+    pub jump_pressed_last_tick: bool,
+    pub crouch_pressed_last_tick: bool,
+}
+
+/// Where the character was at the end of the last two fixed ticks, so that rendering can smooth
+/// between them instead of stepping at the fixed rate.
+#[derive(Component, Reflect)]
+pub struct PlatformingCharacterInterpolation {
+    pub previous_translation: Vec3,
+    pub current_translation: Vec3,
+}
+
+impl PlatformingCharacterInterpolation {
+    pub fn new(translation: Vec3) -> Self {
+        Self {
+            previous_translation: translation,
+            current_translation: translation,
+        }
+    }
+
+    /// `overstep` is how far into the next fixed tick we are, from 0 to 1.
+    pub fn translation(&self, overstep: f32) -> Vec3 {
+        self.previous_translation
+            .lerp(self.current_translation, overstep)
+    }
 }
 
 #[derive(Component, Reflect)]
@@ -61,10 +229,21 @@ pub struct KinematicCharacterPhysics {
     pub orientation: Quat,
 }
 
-#[derive(Reflect)]
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
 pub enum AirSpeed {
-    Grounded { angle: f32, slope_quat: Quat },
-    InAir(f32),
+    Grounded {
+        angle: f32,
+        slope_quat: Quat,
+    },
+    /// Velocity while airborne.
+    InAir(Vec3),
+}
+
+/// A grounded move that's charged up while standing still, then released.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub enum ChargeDash {
+    Idle,
+    Charging { charge: f32 },
 }
 
 #[derive(Component, Reflect)]
@@ -0,0 +1,169 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use bevy::prelude::*;
+
+/// Magic bytes at the start of a recording file.
+const RECORDING_MAGIC: &[u8; 4] = b"WLKY";
+/// Version 2 added the crouch and latched jump flags, and version 3 records every player.
+const RECORDING_VERSION: u8 = 3;
+
+/// Everything one player's character reads from them during one fixed tick.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RecordedInput {
+    /// `PlayerSlot::join_order` of the player. Characters without a `PlayerSlot` count as player
+    /// 0.
+    pub player: u32,
+    /// `PlatformingCharacterControl::move_input`, after it was mapped through the camera.
+    pub move_input: Vec2,
+    pub jump_pressed: bool,
+    pub jump_press_latched: bool,
+    pub crouch_pressed: bool,
+    pub camera_yaw: f32,
+    pub camera_pitch: f32,
+}
+
+/// Every player's input during one fixed tick.
+#[derive(Reflect, Clone, Debug, Default, PartialEq)]
+pub struct RecordedTick {
+    pub players: Vec<RecordedInput>,
+}
+
+/// A sequence of per-tick inputs that can be saved to a file and replayed.
+///
+/// Recordings start from the first fixed tick, so playing one back from startup puts the
+/// characters through exactly the same run.
+///
+/// The file is a 4 byte magic, a version byte, a little-endian `u32` tick count, and then for
+/// each tick a player count byte followed by 21 bytes per player: the player's join order as a
+/// `u32`, `move_input.x`, `move_input.y` as `f32`, a flags byte (bit 0 is jump, bit 1 is crouch,
+/// bit 2 is a latched jump press), then camera yaw and pitch as `f32`.
+#[derive(Reflect, Clone, Debug, Default, PartialEq)]
+pub struct InputRecording {
+    pub ticks: Vec<RecordedTick>,
+}
+
+impl InputRecording {
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(RECORDING_MAGIC)?;
+        writer.write_all(&[RECORDING_VERSION])?;
+        writer.write_all(&(self.ticks.len() as u32).to_le_bytes())?;
+        for tick in &self.ticks {
+            let count = u8::try_from(tick.players.len()).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "too many players to record")
+            })?;
+            writer.write_all(&[count])?;
+            for input in &tick.players {
+                writer.write_all(&input.player.to_le_bytes())?;
+                writer.write_all(&input.move_input.x.to_le_bytes())?;
+                writer.write_all(&input.move_input.y.to_le_bytes())?;
+                let flags = input.jump_pressed as u8
+                    | (input.crouch_pressed as u8) << 1
+                    | (input.jump_press_latched as u8) << 2;
+                writer.write_all(&[flags])?;
+                writer.write_all(&input.camera_yaw.to_le_bytes())?;
+                writer.write_all(&input.camera_pitch.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != RECORDING_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an input recording",
+            ));
+        }
+        let version = read_u8(&mut reader)?;
+        if version != RECORDING_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported input recording version {version}"),
+            ));
+        }
+
+        let count = read_u32(&mut reader)? as usize;
+
+        // The count comes from the file, so don't trust it to size an allocation.
+        let mut ticks = Vec::new();
+        for _ in 0..count {
+            let players = read_u8(&mut reader)?;
+            let mut tick = RecordedTick::default();
+            for _ in 0..players {
+                let player = read_u32(&mut reader)?;
+                let move_input = Vec2::new(read_f32(&mut reader)?, read_f32(&mut reader)?);
+                let flags = read_u8(&mut reader)?;
+                tick.players.push(RecordedInput {
+                    player,
+                    move_input,
+                    jump_pressed: flags & 1 != 0,
+                    crouch_pressed: flags & 2 != 0,
+                    jump_press_latched: flags & 4 != 0,
+                    camera_yaw: read_f32(&mut reader)?,
+                    camera_pitch: read_f32(&mut reader)?,
+                });
+            }
+            ticks.push(tick);
+        }
+        Ok(Self { ticks })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+/// While this resource exists, every fixed tick's input is appended to it, so it grows for as long
+/// as it's around. Recording is opt-in for that reason. Replays start from the characters'
+/// starting state, so it has to exist from the first fixed tick.
+#[derive(Resource, Default)]
+pub struct InputRecorder {
+    pub recording: InputRecording,
+}
+
+/// While this resource exists, the recorded inputs are fed to the characters instead of the
+/// keyboard and gamepad, each to the character of the player who made them. It removes itself when the recording runs out.
+#[derive(Resource)]
+pub struct InputReplay {
+    pub recording: InputRecording,
+    pub next_tick: usize,
+}
+
+impl InputReplay {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            next_tick: 0,
+        }
+    }
+}
@@ -1,10 +1,7 @@
-use bevy::{
-    prelude::*,
-    reflect::{impl_type_path, Reflect},
-};
-use bevy_xpbd_3d::prelude::{PhysicsLayer, RayHitData, ShapeHitData};
+use bevy::{prelude::*, reflect::Reflect};
+use bevy_xpbd_3d::prelude::{PhysicsLayer, ShapeHitData};
 use strum::EnumCount;
-use strum_macros::{EnumCount as EnumCountMacro, EnumIter, EnumTable, FromRepr};
+use strum_macros::{EnumCount as EnumCountMacro, EnumIter, FromRepr};
 
 /// Different kinds of sensor a character has
 #[derive(EnumCountMacro, EnumIter, FromRepr, Reflect, Debug)]
@@ -37,4 +34,6 @@ pub enum MyCollisionLayers {
     Player,
     Enemy,
     Environment,
+    /// Sensor volumes that take over the camera of a player inside them.
+    CameraZone,
 }
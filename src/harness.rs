@@ -0,0 +1,270 @@
+//! Headless simulation harness for the character controller.
+//!
+//! Runs the controller with `MinimalPlugins` and `PhysicsPlugins`, with no window and no GPU, so
+//! movement can be checked from tests instead of by playing the game.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_walky::harness::{CharacterHarness, ScriptedInput};
+//! use bevy_xpbd_3d::prelude::*;
+//!
+//! let mut harness = CharacterHarness::new();
+//! harness.spawn_static_collider(Collider::cuboid(100.0, 0.1, 100.0), Transform::default());
+//! harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+//! let trajectory = harness.run(120, |_tick| ScriptedInput::moving(Vec2::X));
+//! ```
+use std::{ops::Range, time::Duration};
+
+use bevy::{
+    asset::AssetPlugin, gizmos::GizmoPlugin, input::InputPlugin, prelude::*,
+    render::render_resource::Shader, scene::ScenePlugin, time::TimeUpdateStrategy,
+};
+use bevy_xpbd_3d::prelude::*;
+
+use crate::{
+    components::player::{
+        physics::{AirSpeed, PlatformingCharacterControl, PlatformingCharacterPhysics},
+        replay::{InputRecorder, InputRecording, InputReplay},
+        sensors::MyCollisionLayers,
+    },
+    systems::player::spawn::platforming_character_bundle,
+    PlatformingCharacterPlugin,
+};
+
+/// Rate the harness runs `FixedUpdate` at. Each tick is exactly one fixed update.
+pub const HARNESS_TICK_HZ: f64 = 64.0;
+
+/// Input to feed the character for a single tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScriptedInput {
+    /// World-space XZ direction, like `PlatformingCharacterControl::move_input`.
+    pub move_input: Vec2,
+    pub jump_pressed: bool,
+    pub crouch_pressed: bool,
+}
+
+impl ScriptedInput {
+    pub fn moving(direction: Vec2) -> Self {
+        Self {
+            move_input: direction,
+            jump_pressed: false,
+            crouch_pressed: false,
+        }
+    }
+
+    pub fn with_jump(mut self, jump_pressed: bool) -> Self {
+        self.jump_pressed = jump_pressed;
+        self
+    }
+
+    pub fn with_crouch(mut self, crouch_pressed: bool) -> Self {
+        self.crouch_pressed = crouch_pressed;
+        self
+    }
+}
+
+/// State of the character at the end of a tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TickSample {
+    pub tick: u32,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub air_speed: AirSpeed,
+}
+
+pub struct CharacterHarness {
+    app: App,
+    character: Option<Entity>,
+    tick: u32,
+}
+
+impl CharacterHarness {
+    pub fn new() -> Self {
+        Self::with_frame_rate(HARNESS_TICK_HZ)
+    }
+
+    /// Like [`CharacterHarness::new`], but each [`CharacterHarness::tick`] advances one frame at
+    /// `frame_hz` instead of one fixed tick. A frame runs as many fixed ticks as fit into it, like
+    /// the game does at that frame rate.
+    pub fn with_frame_rate(frame_hz: f64) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            AssetPlugin::default(),
+            ScenePlugin,
+            InputPlugin,
+        ))
+        // Gizmos are drawn by the controller systems, so their plugin needs to be around even
+        // though nothing will render them.
+        .init_asset::<Shader>()
+        .init_asset::<Mesh>()
+        .add_plugins(GizmoPlugin)
+        .add_plugins(PhysicsPlugins::new(FixedUpdate))
+        .add_plugins(PlatformingCharacterPlugin);
+
+        let timestep = Duration::from_secs_f64(1.0 / HARNESS_TICK_HZ);
+        let frame = Duration::from_secs_f64(1.0 / frame_hz);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(frame))
+            .insert_resource(Time::<Fixed>::from_duration(timestep))
+            // Physics runs in FixedUpdate, so step it by exactly the fixed timestep each time.
+            .insert_resource(Time::new_with(Physics::from_timestep(
+                TimestepMode::FixedOnce { delta: timestep },
+            )));
+
+        app.finish();
+        app.cleanup();
+        // The first update runs startup and has no time delta, so nothing moves yet.
+        app.update();
+
+        Self {
+            app,
+            character: None,
+            tick: 0,
+        }
+    }
+
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Adds a piece of level geometry the character can stand on and run into.
+    pub fn spawn_static_collider(&mut self, collider: Collider, transform: Transform) -> Entity {
+        self.app
+            .world
+            .spawn((
+                collider,
+                RigidBody::Static,
+                TransformBundle::from_transform(transform),
+                CollisionLayers::new(
+                    [MyCollisionLayers::Environment],
+                    [MyCollisionLayers::Player],
+                ),
+            ))
+            .id()
+    }
+
+    /// Adds a curved surface in the XY plane, built from `segments` flat pieces around `center`.
+    /// Angles are measured from straight below the center, so `0.0..PI` is a quarter pipe that
+    /// carries on up into a ceiling, and `0.0..2.0 * PI` is a full loop. The inside of the curve
+    /// faces the center.
+    pub fn spawn_arc(&mut self, center: Vec3, radius: f32, angles: Range<f32>, segments: u32) {
+        let thickness = 0.1;
+        let step = (angles.end - angles.start) / segments as f32;
+        // Overlap the pieces a little, so there are no gaps to catch the casts.
+        let length = 2.0 * radius * (step.abs() * 0.5).sin() * 1.05;
+        for i in 0..segments {
+            let angle = angles.start + step * (i as f32 + 0.5);
+            let rotation = Quat::from_rotation_z(angle);
+            let surface = center + Vec3::new(angle.sin(), -angle.cos(), 0.0) * radius;
+            let translation = surface - rotation.mul_vec3(Vec3::Y) * thickness * 0.5;
+            self.spawn_static_collider(
+                Collider::cuboid(length, thickness, 10.0),
+                Transform::from_translation(translation).with_rotation(rotation),
+            );
+        }
+    }
+
+    /// Spawns the character that [`CharacterHarness::tick`] drives and samples.
+    pub fn spawn_character(&mut self, transform: Transform) -> Entity {
+        let character = self
+            .app
+            .world
+            .spawn(platforming_character_bundle(transform))
+            .id();
+        self.character = Some(character);
+        character
+    }
+
+    pub fn character(&self) -> Entity {
+        self.character
+            .expect("spawn_character must be called before driving the character")
+    }
+
+    /// Feeds `input` to the character and advances one fixed tick.
+    pub fn tick(&mut self, input: ScriptedInput) -> TickSample {
+        let character = self.character();
+        let mut control = self
+            .app
+            .world
+            .get_mut::<PlatformingCharacterControl>(character)
+            .unwrap();
+        control.move_input = input.move_input;
+        control.jump_pressed = input.jump_pressed;
+        control.crouch_pressed = input.crouch_pressed;
+
+        self.step()
+    }
+
+    fn step(&mut self) -> TickSample {
+        self.app.update();
+        self.tick += 1;
+        self.sample()
+    }
+
+    /// Runs `ticks` ticks, asking `script` for the input of each one.
+    pub fn run(
+        &mut self,
+        ticks: u32,
+        mut script: impl FnMut(u32) -> ScriptedInput,
+    ) -> Vec<TickSample> {
+        (0..ticks)
+            .map(|_| {
+                let input = script(self.tick);
+                self.tick(input)
+            })
+            .collect()
+    }
+
+    /// Plays `recording` back through `InputReplay` and returns one sample per recorded tick.
+    pub fn play(&mut self, recording: InputRecording) -> Vec<TickSample> {
+        let ticks = recording.ticks.len();
+        self.app.insert_resource(InputReplay::new(recording));
+        (0..ticks).map(|_| self.step()).collect()
+    }
+
+    /// Starts capturing every tick's input, for [`CharacterHarness::take_recording`]. Call it
+    /// before the first tick, so the recording replays from the character's starting state.
+    pub fn start_recording(&mut self) {
+        self.app.insert_resource(InputRecorder::default());
+    }
+
+    pub fn take_recording(&mut self) -> Option<InputRecording> {
+        self.app
+            .world
+            .remove_resource::<InputRecorder>()
+            .map(|recorder| recorder.recording)
+    }
+
+    pub fn sample(&self) -> TickSample {
+        let character = self.character();
+        let world = &self.app.world;
+        // Physics adds the position and velocity on the first fixed tick, which a frame that's
+        // shorter than a tick might not have run yet.
+        TickSample {
+            tick: self.tick,
+            position: world.get::<Position>(character).map_or_else(
+                || world.get::<Transform>(character).unwrap().translation,
+                |position| position.0,
+            ),
+            velocity: world
+                .get::<LinearVelocity>(character)
+                .map_or(Vec3::ZERO, |velocity| velocity.0),
+            air_speed: world
+                .get::<PlatformingCharacterPhysics>(character)
+                .unwrap()
+                .air_speed,
+        }
+    }
+}
+
+impl Default for CharacterHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,15 @@
+//! Sonic-style platforming character controller for Bevy, on top of bevy_xpbd.
+//!
+//! Add [`PlatformingCharacterPlugin`] after `PhysicsPlugins::new(FixedUpdate)`, then spawn a
+//! character with the components from [`systems::player::spawn::spawn_player`].
+
+// Systems take their resources as arguments and their queries as tuples, so these fire on ordinary
+// Bevy systems.
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+pub mod components;
+pub mod harness;
+pub mod plugins;
+pub mod systems;
+
+pub use plugins::character::{PlatformingCharacterPlugin, PlatformingCharacterSet};
@@ -1,24 +1,30 @@
-pub mod components;
-pub mod systems;
-
-use bevy::render::settings::{Backends, RenderCreation, WgpuSettings};
+//! Demo scene for the bevy_walky character controller.
+use bevy::render::settings::RenderCreation;
+#[cfg(target_os = "windows")]
+use bevy::render::settings::{Backends, WgpuSettings};
 use bevy::transform::TransformSystem;
 use bevy::{prelude::*, render::RenderPlugin};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use bevy_xpbd_3d::{prelude::*, PhysicsSchedule, SubstepSchedule, SubstepSet};
-use components::camera::{OrbitCameraTarget, ViewpointMappable, ViewpointMappedInput};
-use components::player::sensors::CharacterSensorArray;
-use smooth_bevy_cameras::{
-    controllers::{orbit::OrbitCameraPlugin, unreal::UnrealCameraPlugin},
-    LookTransform, LookTransformBundle, LookTransformPlugin,
-};
-use systems::{
-    player::physics::{
-        handle_collisions, update_platforming_accel_from_controls,
-        update_platforming_kinematic_from_physics, update_platforming_physics,
+use bevy_walky::{
+    components::{
+        camera::SplitScreen,
+        input::InputBindings,
+        player::{
+            animation::{AnimationStateMachine, AnimationStateMachineLoader},
+            replay::{InputRecorder, InputRecording, InputReplay},
+        },
+    },
+    systems::{
+        self,
+        player::{control::BINDINGS_PATH, replay::RECORDING_PATH},
     },
-    world::camera::{project_input_camera, update_camera},
+    PlatformingCharacterPlugin, PlatformingCharacterSet,
+};
+use bevy_xpbd_3d::prelude::*;
+use smooth_bevy_cameras::{
+    controllers::unreal::UnrealCameraPlugin, LookTransform, LookTransformPlugin,
 };
+use systems::world::camera::update_camera;
 
 #[cfg(target_os = "windows")]
 fn render_plugin() -> RenderPlugin {
@@ -38,97 +44,125 @@ fn render_plugin() -> RenderPlugin {
     }
 }
 
+/// Loads the file passed as `--replay <path>`, if there is one.
+fn replay_from_args() -> Option<InputReplay> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay").skip(1);
+    let path = args.next()?;
+    match InputRecording::load(&path) {
+        Ok(recording) => {
+            info!("replaying {} ticks from {}", recording.ticks.len(), path);
+            Some(InputReplay::new(recording))
+        }
+        Err(e) => {
+            warn!("failed to load replay {}: {:?}", path, e);
+            None
+        }
+    }
+}
+
+/// Loads the controls saved by an earlier rebind, if there are any.
+fn saved_bindings() -> Option<InputBindings> {
+    match InputBindings::load(BINDINGS_PATH) {
+        Ok(bindings) => {
+            info!("loaded bindings from {}", BINDINGS_PATH);
+            Some(bindings)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("failed to load bindings from {}: {:?}", BINDINGS_PATH, e);
+            None
+        }
+    }
+}
+
 fn main() {
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        // fill the entire browser window
-                        fit_canvas_to_parent: true,
-                        // don't hijack keyboard shortcuts like F5, F6, F12, Ctrl+R etc.
-                        prevent_default_event_handling: false,
-                        ..default()
-                    }),
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    // fill the entire browser window
+                    fit_canvas_to_parent: true,
+                    // don't hijack keyboard shortcuts like F5, F6, F12, Ctrl+R etc.
+                    prevent_default_event_handling: false,
                     ..default()
-                })
-                .set(render_plugin()),
-        )
-        .add_plugins(LookTransformPlugin)
-        .add_plugins(WorldInspectorPlugin::new())
-        .add_plugins(UnrealCameraPlugin::default())
-        .add_plugins(PhysicsPlugins::default())
-        // .add_plugins(PhysicsDebugPlugin::default())
-        .register_type::<components::player::physics::PlatformingCharacterPhysics>()
-        .register_type::<components::player::physics::PlatformingCharacterPhysicsAccel>()
-        .register_type::<components::player::physics::PlatformingCharacterValues>()
-        .register_type::<components::player::physics::PlatformingCharacterControl>()
-        .register_type::<components::player::physics::PlatformingCharacterAnimationFlags>()
-        .register_type::<components::player::animation::Animated>()
-        .register_type::<CharacterSensorArray>()
-        .register_type::<LookTransform>()
-        .register_type::<OrbitCameraTarget>()
-        .register_type::<ViewpointMappable>()
-        .register_type::<ViewpointMappedInput>()
-        .add_systems(Startup, systems::world::camera::setup_camera)
-        .add_systems(Startup, systems::world::scene::setup_scene)
-        .add_systems(Startup, systems::player::spawn::spawn_player)
-        .add_systems(Startup, systems::world::scene::setup_physics)
-        .add_systems(Startup, systems::player::animation::setup_animations)
-        .add_systems(
-            Update,
-            systems::world::physics_fixup::fixup_nested_colliders,
-        )
-        .add_systems(
-            Update,
-            systems::world::physics_fixup::reapply_collider_transform,
-        )
-        .add_systems(Update, systems::player::control::character_movement)
-        .add_systems(Update, systems::player::control::character_gamepad)
-        .add_systems(Update, systems::player::animation::character_animation)
-        .add_systems(Update, update_camera)
-        .add_systems(Update, project_input_camera)
-        .add_systems(
-            // constraints avoid camera jitter: https://github.com/Jondolf/bevy_xpbd/issues/211#issuecomment-1789342920
-            PostUpdate,
-            update_camera
-                .after(PhysicsSet::Sync)
-                .before(TransformSystem::TransformPropagate),
-        )
-        // .add_systems(
-        //     PostProcessCollisions,
-        //     systems::player::sensors::update_sensors, //.after(systems::player::sensors::position_sensors),
-        // )
-        // .add_systems(
-        //     PostProcessCollisions,
-        //     systems::player::sensors::position_sensors
-        //         .after(TransformSystem::TransformPropagate)
-        //         .after(PhysicsSet::Sync),
-        // )
-        .add_systems(FixedUpdate, systems::player::physics::update_floor)
-        .add_systems(FixedUpdate, update_platforming_accel_from_controls)
-        .add_systems(
-            FixedUpdate,
-            update_platforming_physics.after(update_platforming_accel_from_controls),
-        )
-        .add_systems(
-            PostUpdate,
-            update_platforming_kinematic_from_physics
-                .after(update_platforming_physics)
-                .after(PhysicsSet::Sync)
-                .before(TransformSystem::TransformPropagate),
+                }),
+                ..default()
+            })
+            .set(render_plugin()),
+    )
+    .add_plugins(LookTransformPlugin)
+    .add_plugins(WorldInspectorPlugin::new())
+    .add_plugins(UnrealCameraPlugin::default())
+    .add_plugins(PhysicsPlugins::new(FixedUpdate))
+    // Physics runs in FixedUpdate, so step it by exactly the fixed timestep each time.
+    .insert_resource(Time::new_with(Physics::from_timestep(
+        TimestepMode::FixedOnce {
+            delta: Time::<Fixed>::default().timestep(),
+        },
+    )))
+    // .add_plugins(PhysicsDebugPlugin::default())
+    .add_plugins(PlatformingCharacterPlugin)
+    .register_type::<LookTransform>()
+    .init_asset::<AnimationStateMachine>()
+    .init_asset_loader::<AnimationStateMachineLoader>()
+    .init_resource::<SplitScreen>()
+    .add_systems(Startup, systems::world::camera::setup_camera)
+    .add_systems(Startup, systems::world::scene::setup_scene)
+    .add_systems(Startup, systems::player::spawn::spawn_player)
+    .add_systems(Startup, systems::world::scene::setup_physics)
+    .add_systems(Startup, systems::player::animation::setup_animations)
+    .add_systems(
+        Update,
+        systems::world::physics_fixup::fixup_nested_colliders,
+    )
+    .add_systems(
+        Update,
+        systems::world::physics_fixup::reapply_collider_transform,
+    )
+    .add_systems(Update, systems::player::replay::recording_hotkey)
+    .add_systems(
+        Update,
+        (
+            systems::player::spawn::join_players,
+            systems::player::spawn::leave_players,
+            systems::player::control::toggle_mouse_look
+                .before(systems::player::control::capture_rebind),
         )
-        .add_systems(
-            PostUpdate,
-            systems::player::physics::push_out_of_ground
-                .after(PhysicsSet::Sync)
-                .before(TransformSystem::TransformPropagate),
+            .before(PlatformingCharacterSet::Input),
+    )
+    .add_systems(Update, systems::player::animation::character_animation)
+    .add_systems(
+        Update,
+        (
+            systems::world::camera::spawn_player_cameras,
+            systems::world::camera::layout_split_screen,
+            systems::world::camera::update_camera_zones,
         )
-        .add_systems(
-            SubstepSchedule,
-            systems::player::physics::handle_collisions
-                .in_set(SubstepSet::SolveUserConstraints)
-                .after(systems::player::physics::push_out_of_ground),
-        )
-        .run();
+            .chain(),
+    )
+    .add_systems(
+        // Follow the character where it's drawn this frame, after it was interpolated between
+        // fixed ticks, or the camera jitters against it.
+        PostUpdate,
+        update_camera
+            .after(PlatformingCharacterSet::Interpolate)
+            .before(TransformSystem::TransformPropagate),
+    );
+
+    if let Some(bindings) = saved_bindings() {
+        app.insert_resource(bindings);
+    }
+    if let Some(replay) = replay_from_args() {
+        app.insert_resource(replay);
+    }
+    // Record from the very first tick, so a saved recording replays from the same start.
+    if std::env::args().any(|arg| arg == "--record") {
+        info!(
+            "recording inputs, press F9 to save them to {}",
+            RECORDING_PATH
+        );
+        app.insert_resource(InputRecorder::default());
+    }
+    app.run();
 }
@@ -0,0 +1,191 @@
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_xpbd_3d::{prelude::*, SubstepSchedule, SubstepSet};
+
+use crate::{
+    components::{
+        camera::{
+            CameraZone, OrbitCameraTarget, PlayerCamera, SplitScreen, ViewpointMappable,
+            ViewpointMappedInput,
+        },
+        input::{ActionState, InputBindings, MouseLook, PlayerSlot},
+        player::{
+            animation::Animated,
+            homing::{HomingAttack, HomingTargetEvent, HomingTargetable},
+            physics::{
+                PlatformingCharacterAnimationFlags, PlatformingCharacterControl,
+                PlatformingCharacterInterpolation, PlatformingCharacterPhysics,
+                PlatformingCharacterPhysicsAccel, PlatformingCharacterValues,
+            },
+            replay::{InputRecorder, InputReplay},
+            sensors::CharacterSensorArray,
+        },
+    },
+    systems::{
+        player::{
+            control::{capture_rebind, character_input, PendingRebind},
+            homing::{acquire_homing_targets, draw_homing_reticle, update_homing_dash},
+            physics::{
+                handle_collisions, interpolate_character_models, push_out_of_ground,
+                store_interpolation_translations, update_floor,
+                update_platforming_accel_from_controls, update_platforming_kinematic_from_physics,
+                update_platforming_physics,
+            },
+            replay::{record_inputs, replay_inputs},
+        },
+        world::camera::{auto_follow_camera, project_input_camera},
+    },
+};
+
+/// The phases of the platforming character controller, in the order they run.
+///
+/// Systems outside of this plugin can be ordered relative to these, e.g.
+/// `.before(PlatformingCharacterSet::Accel)` to feed in input from somewhere else.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlatformingCharacterSet {
+    /// Reads devices through `InputBindings` into `PlatformingCharacterControl`. Runs in `Update`,
+    /// and is skipped while an `InputReplay` is playing. While a `PendingRebind` is waiting for a
+    /// button, it reads nothing pressed.
+    Input,
+    /// Turns controls into accelerations. Runs in `FixedUpdate`, before the physics step.
+    Accel,
+    /// Integrates accelerations into ground and air speed. Runs in `FixedUpdate`, before the
+    /// physics step.
+    Integrate,
+    /// Casts against the world and turns speeds into a `LinearVelocity`. Runs in `FixedUpdate`,
+    /// after the physics step.
+    Kinematic,
+    /// Keeps the character out of the ground and walls. Runs in `FixedUpdate` after the physics
+    /// step, and in the physics `SubstepSchedule`.
+    Depenetrate,
+    /// Records where the character ended up each fixed tick (in `FixedUpdate`), and smooths the
+    /// character's model between ticks (in `PostUpdate`). Order cameras after this.
+    Interpolate,
+}
+
+/// Sonic-style platforming character controller.
+///
+/// Every step of the controller runs at the fixed timestep, so the physics must too: add
+/// `PhysicsPlugins::new(FixedUpdate)` before this plugin, since it adds systems to the physics
+/// substep schedule, and set `Time<Physics>` to step once by the fixed timestep each time it
+/// runs, with `TimestepMode::FixedOnce`.
+pub struct PlatformingCharacterPlugin;
+
+impl Plugin for PlatformingCharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PlatformingCharacterPhysics>()
+            .register_type::<PlatformingCharacterPhysicsAccel>()
+            .register_type::<PlatformingCharacterValues>()
+            .register_type::<PlatformingCharacterControl>()
+            .register_type::<PlatformingCharacterAnimationFlags>()
+            .register_type::<PlatformingCharacterInterpolation>()
+            .register_type::<Animated>()
+            .register_type::<HomingAttack>()
+            .register_type::<HomingTargetable>()
+            .register_type::<CharacterSensorArray>()
+            .register_type::<OrbitCameraTarget>()
+            .register_type::<ViewpointMappable>()
+            .register_type::<PlayerCamera>()
+            .register_type::<CameraZone>()
+            .register_type::<SplitScreen>()
+            .register_type::<ViewpointMappedInput>()
+            .register_type::<ActionState>()
+            .register_type::<InputBindings>()
+            .register_type::<MouseLook>()
+            .register_type::<PlayerSlot>();
+
+        app.init_resource::<InputBindings>();
+        app.init_resource::<MouseLook>();
+
+        app.add_event::<HomingTargetEvent>();
+
+        app.configure_sets(
+            Update,
+            PlatformingCharacterSet::Input.run_if(not(resource_exists::<InputReplay>())),
+        )
+        .configure_sets(
+            FixedUpdate,
+            (
+                PlatformingCharacterSet::Accel,
+                PlatformingCharacterSet::Integrate,
+            )
+                .chain()
+                .before(PhysicsSet::Prepare),
+        )
+        .configure_sets(
+            FixedUpdate,
+            (
+                PlatformingCharacterSet::Kinematic,
+                PlatformingCharacterSet::Depenetrate,
+                PlatformingCharacterSet::Interpolate,
+            )
+                .chain()
+                .after(PhysicsSet::Sync),
+        )
+        .configure_sets(
+            PostUpdate,
+            PlatformingCharacterSet::Interpolate.before(TransformSystem::TransformPropagate),
+        )
+        .configure_sets(
+            SubstepSchedule,
+            PlatformingCharacterSet::Depenetrate.in_set(SubstepSet::SolveUserConstraints),
+        );
+
+        app.add_systems(
+            Update,
+            (character_input, auto_follow_camera, project_input_camera)
+                .chain()
+                .in_set(PlatformingCharacterSet::Input),
+        )
+        .add_systems(
+            Update,
+            capture_rebind
+                .run_if(resource_exists::<PendingRebind>())
+                .before(PlatformingCharacterSet::Input),
+        )
+        .add_systems(Update, draw_homing_reticle)
+        .add_systems(
+            FixedUpdate,
+            (
+                replay_inputs.run_if(resource_exists::<InputReplay>()),
+                record_inputs.run_if(resource_exists::<InputRecorder>()),
+            )
+                .chain()
+                .before(PlatformingCharacterSet::Accel),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                update_floor,
+                acquire_homing_targets.before(update_platforming_accel_from_controls),
+                update_platforming_accel_from_controls,
+            )
+                .in_set(PlatformingCharacterSet::Accel),
+        )
+        .add_systems(
+            FixedUpdate,
+            (update_platforming_physics, update_homing_dash)
+                .chain()
+                .in_set(PlatformingCharacterSet::Integrate),
+        )
+        .add_systems(
+            FixedUpdate,
+            update_platforming_kinematic_from_physics.in_set(PlatformingCharacterSet::Kinematic),
+        )
+        .add_systems(
+            FixedUpdate,
+            push_out_of_ground.in_set(PlatformingCharacterSet::Depenetrate),
+        )
+        .add_systems(
+            FixedUpdate,
+            store_interpolation_translations.in_set(PlatformingCharacterSet::Interpolate),
+        )
+        .add_systems(
+            PostUpdate,
+            interpolate_character_models.in_set(PlatformingCharacterSet::Interpolate),
+        )
+        .add_systems(
+            SubstepSchedule,
+            handle_collisions.in_set(PlatformingCharacterSet::Depenetrate),
+        );
+    }
+}
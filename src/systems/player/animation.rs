@@ -1,86 +1,120 @@
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{gltf::Gltf, prelude::*};
 
 use crate::components::player::{
-    animation::Animated,
+    animation::{Animated, AnimationContext, AnimationStateMachine},
     physics::{
-        FloorInfo, KinematicCharacterPhysics, PlatformingCharacterControl,
-        PlatformingCharacterPhysics, PlatformingCharacterValues,
+        AirSpeed, ChargeDash, PlatformingCharacterAnimationFlags, PlatformingCharacterPhysics,
+        PlatformingCharacterValues,
     },
 };
 
+/// Steps each character's animation state machine, and plays the clip of the state it's in.
 pub fn character_animation(
-    mut characters: Query<(
-        &PlatformingCharacterControl,
+    characters: Query<(
         &PlatformingCharacterPhysics,
         &PlatformingCharacterValues,
+        &PlatformingCharacterAnimationFlags,
     )>,
-    mut char_anims: Query<(&mut Animated, &mut Transform, &Parent)>,
+    mut char_anims: Query<(Entity, &mut Animated, &mut Transform, &Parent)>,
+    children: Query<&Children>,
     mut animation_players: Query<&mut AnimationPlayer>,
     animations: Res<Animations>,
+    state_machines: Res<Assets<AnimationStateMachine>>,
+    gltfs: Res<Assets<Gltf>>,
+    time: Res<Time>,
 ) {
-    for (mut anim_state, mut anim_transform, parent) in char_anims.iter_mut() {
-        if let Ok((control, physics, values)) = characters.get(parent.get()) {
-            anim_transform.rotation = physics.overall_rotation;
+    let Some(machine) = state_machines.get(&animations.state_machine) else {
+        return;
+    };
+    let Some(gltf) = gltfs.get(&machine.gltf_handle) else {
+        return;
+    };
 
-            match physics.air_speed {
-                crate::components::player::physics::AirSpeed::Grounded { angle, slope_quat } => {
-                    if physics.ground_speed.length() > 1.0 {
-                        anim_state.current_animation = 1;
-                        anim_state.speed = (physics.ground_speed.length() / values.top_speed) * 3.0;
-                    } else {
-                        anim_state.current_animation = 0;
-                        anim_state.speed = 1.0;
-                    }
-                }
-                crate::components::player::physics::AirSpeed::InAir(air_speed) => {
-                    if air_speed > 0.0 {
-                        anim_state.current_animation = 2;
-                        anim_state.speed = air_speed / 10.0;
-                    } else {
-                        anim_state.current_animation = 3;
-                        anim_state.speed = air_speed / 10.0 * -1.0;
-                    }
-                }
-            }
+    for (model, mut anim_state, mut anim_transform, parent) in char_anims.iter_mut() {
+        let Ok((physics, values, flags)) = characters.get(parent.get()) else {
+            continue;
+        };
+        anim_transform.rotation = physics.overall_rotation;
+        anim_state.time_in_state += time.delta_seconds();
 
-            let current = &animations.0[anim_state.current_animation];
+        let context = AnimationContext {
+            grounded: matches!(physics.air_speed, AirSpeed::Grounded { .. }),
+            rolling: physics.rolling,
+            skidding: flags.skidding,
+            dash_charge: match physics.charge_dash {
+                ChargeDash::Idle => None,
+                ChargeDash::Charging { charge } => Some(charge / values.charge_dash_max_charge),
+            },
+            speed: physics.speed(),
+            top_speed: values.top_speed,
+            vertical_speed: match physics.air_speed {
+                AirSpeed::Grounded { .. } => 0.0,
+                AirSpeed::InAir(velocity) => velocity.dot(values.up()),
+            },
+            time_in_state: anim_state.time_in_state,
+        };
 
-            for mut player in animation_players.iter_mut() {
-                if !player.is_playing_clip(current) {
-                    // switch player
-                    info!("switch animation to {:?}", anim_state.current_animation);
-                    player
-                        .play_with_transition(current.clone_weak(), Duration::from_millis(100))
-                        .repeat();
-                }
-                player.set_speed(anim_state.speed);
+        let mut entered = false;
+        if machine.state(&anim_state.state).is_none() {
+            anim_state.state = machine.initial_state.clone();
+            anim_state.time_in_state = 0.0;
+            entered = true;
+        }
+        if let Some(next) = machine.next_state(&anim_state.state, &context) {
+            info!("switch animation from {} to {}", anim_state.state, next);
+            anim_state.state = next.to_string();
+            anim_state.time_in_state = 0.0;
+            entered = true;
+        }
+        let Some(state) = machine.state(&anim_state.state) else {
+            continue;
+        };
+        anim_state.speed = state.speed.evaluate(&context);
+
+        let Some(clip) = gltf.named_animations.get(&state.clip) else {
+            if entered {
+                warn!("{} has no animation named {:?}", machine.gltf, state.clip);
             }
+            continue;
+        };
 
-            // anim_transform.rotation = match physics.air_speed {
-            //     crate::components::player::physics::AirSpeed::Grounded { angle, slope_quat } => {
-            //         kinematic_physics.orientation
-            //     }
-            //     crate::components::player::physics::AirSpeed::InAir(_) => {
-            //         let dir_3d =
-            //             Vec3::new(physics.ground_direction.x, 0.0, physics.ground_direction.y);
-            //         Quat::from_rotation_arc(Vec3::Z, dir_3d)
-            //     }
-            // }
+        // Only drive the players in this character's own model.
+        let mut players = animation_players.iter_many_mut(children.iter_descendants(model));
+        while let Some(mut player) = players.fetch_next() {
+            if entered || !player.is_playing_clip(clip) {
+                player.play_with_transition(
+                    clip.clone_weak(),
+                    Duration::from_secs_f32(state.blend_time),
+                );
+                if state.repeat {
+                    player.repeat();
+                }
+            }
+            player.set_speed(anim_state.speed);
         }
+
+        // anim_transform.rotation = match physics.air_speed {
+        //     crate::components::player::physics::AirSpeed::Grounded { angle, slope_quat } => {
+        //         kinematic_physics.orientation
+        //     }
+        //     crate::components::player::physics::AirSpeed::InAir(_) => {
+        //         let dir_3d =
+        //             Vec3::new(physics.ground_direction.x, 0.0, physics.ground_direction.y);
+        //         Quat::from_rotation_arc(Vec3::Z, dir_3d)
+        //     }
+        // }
     }
 }
 
 #[derive(Resource)]
-pub struct Animations(Vec<Handle<AnimationClip>>);
+pub struct Animations {
+    pub state_machine: Handle<AnimationStateMachine>,
+}
 
 pub fn setup_animations(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(Animations(vec![
-        asset_server.load("degauss.glb#Animation0"),
-        asset_server.load("degauss.glb#Animation1"),
-        asset_server.load("degauss.glb#Animation2"),
-        asset_server.load("degauss.glb#Animation3"),
-        asset_server.load("degauss.glb#Animation4"),
-    ]));
+    commands.insert_resource(Animations {
+        state_machine: asset_server.load("degauss.anim.ron"),
+    });
 }
@@ -1,140 +1,207 @@
-use std::f32::consts::PI;
-
-use bevy::{input::mouse::MouseMotion, prelude::*};
-use bevy_xpbd_3d::{math::Scalar, prelude::*};
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use bevy_inspector_egui::bevy_egui::EguiContexts;
 
 use crate::components::{
     camera::{OrbitCameraTarget, ViewpointMappedInput},
-    player::physics::{
-        AirSpeed, KinematicCharacterPhysics, PlatformingCharacterControl,
-        PlatformingCharacterPhysics, PlatformingCharacterPhysicsAccel, PlatformingCharacterValues,
-    },
+    input::{Action, ActionState, Binding, InputBindings, InputSources, MouseLook, PlayerSlot},
+    player::physics::PlatformingCharacterControl,
 };
 
-pub fn character_movement(
-    mut character_control: Query<(&mut PlatformingCharacterControl, &mut ViewpointMappedInput)>,
-    mut camera_targets: Query<&mut OrbitCameraTarget>,
-    keys: Res<Input<KeyCode>>,
-    mut mouse: EventReader<MouseMotion>,
-) {
-    if keys.just_pressed(KeyCode::Space) {
-        // jump
-        //controller.translation = Some(Vec3::new(0.0, 1.5, 0.0));
-    } else {
-        // todo: apply gravity instead of just setting this
-        //controller.translation = Some(Vec3::new(0.0, -0.5, 0.0));
-    }
-    let mut keyboardDirection = Vec2::new(0.0, 0.0);
-    if keys.pressed(KeyCode::Up) {
-        keyboardDirection += Vec2 { x: 0.0, y: 1.0 }
-    }
-    if keys.pressed(KeyCode::Down) {
-        keyboardDirection += Vec2 { x: 0.0, y: -1.0 }
-    }
-    if keys.pressed(KeyCode::Left) {
-        keyboardDirection += Vec2 { x: -1.0, y: 0.0 }
-    }
-    if keys.pressed(KeyCode::Right) {
-        keyboardDirection += Vec2 { x: 1.0, y: 0.0 }
-    }
-    if keyboardDirection.length() > 0.0 {
-        for (_, mut vmi) in character_control.iter_mut() {
-            vmi.move_input = keyboardDirection.normalize_or_zero();
-        }
-    }
+/// Where rebound controls are saved to, and loaded from at startup.
+pub const BINDINGS_PATH: &str = "input_bindings.ron";
 
-    let mut cursor_delta = Vec2::ZERO;
-    for event in mouse.read() {
-        cursor_delta += event.delta;
-    }
+/// How fast the camera turns for a full stick of `Look`, in radians per second.
+const LOOK_YAW_SPEED: f32 = 0.6;
+const LOOK_PITCH_SPEED: f32 = 0.42;
 
-    // disable mouse look
-    /*
-       const mouse_look_factor: f32 = 0.001;
-       if cursor_delta.length() > 0.3 {
-           for mut c in &mut camera_targets {
-               if c.active {
-                   c.pitch += cursor_delta.y * mouse_look_factor;
-                   c.yaw += cursor_delta.x * mouse_look_factor;
-               }
-           }
-       }
-    */
-}
+/// How the cursor is held for mouse look. Windows can only confine the cursor to the window, not
+/// lock it in place.
+#[cfg(target_os = "windows")]
+const MOUSE_LOOK_GRAB_MODE: CursorGrabMode = CursorGrabMode::Confined;
+#[cfg(not(target_os = "windows"))]
+const MOUSE_LOOK_GRAB_MODE: CursorGrabMode = CursorGrabMode::Locked;
+
+/// While this resource exists, the next key or button pressed is bound to the action, and the
+/// bindings are saved to [`BINDINGS_PATH`]. Escape cancels. Only button actions can be bound
+/// this way, since a single press can't make a stick for `Move` or `Look`.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PendingRebind(pub Action);
 
-pub fn character_gamepad(
-    mut character_control: Query<(&mut PlatformingCharacterControl, &mut ViewpointMappedInput)>,
-    mut camera_targets: Query<&mut OrbitCameraTarget>,
-    axes: Res<Axis<GamepadAxis>>,
-    buttons: Res<Input<GamepadButton>>,
+/// Resolves the bindings against the devices in each character's `PlayerSlot`, and feeds the
+/// actions into its control and camera. Mouse movement only reaches `Look` while [`MouseLook`]
+/// is active. While a [`PendingRebind`] is waiting, the devices are picking a binding instead,
+/// so every character sees nothing pressed.
+pub fn character_input(
+    bindings: Res<InputBindings>,
+    mouse_look: Res<MouseLook>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
     gamepads: Res<Gamepads>,
+    pending_rebind: Option<Res<PendingRebind>>,
+    time: Res<Time>,
+    mut characters: Query<(
+        Option<&PlayerSlot>,
+        &mut ActionState,
+        &mut PlatformingCharacterControl,
+        &mut ViewpointMappedInput,
+        Option<&mut OrbitCameraTarget>,
+    )>,
 ) {
-    for gamepad in gamepads.iter() {
-        // The joysticks are represented using a separate axis for X and Y
-        let axis_lx = GamepadAxis {
-            gamepad,
-            axis_type: GamepadAxisType::LeftStickX,
-        };
-        let axis_ly = GamepadAxis {
-            gamepad,
-            axis_type: GamepadAxisType::LeftStickY,
+    let all_gamepads: Vec<Gamepad> = gamepads.iter().collect();
+    let rebinding = pending_rebind.is_some();
+    let delta = time.delta_seconds();
+    // Bindings read the mouse as a speed, like a stick, so it turns the camera as far at any
+    // frame rate.
+    let mouse_motion: Vec2 = mouse_motion.read().map(|event| event.delta).sum();
+    let mouse_motion = if delta > 0.0 {
+        mouse_motion / delta
+    } else {
+        Vec2::ZERO
+    };
+
+    for (slot, mut actions, mut control, mut vmi, camera_target) in characters.iter_mut() {
+        let (keyboard_mouse, gamepads) = match slot {
+            Some(PlayerSlot {
+                keyboard_mouse,
+                gamepad: Some(gamepad),
+                ..
+            }) => (*keyboard_mouse, std::slice::from_ref(gamepad)),
+            Some(PlayerSlot {
+                keyboard_mouse,
+                gamepad: None,
+                ..
+            }) => (*keyboard_mouse, &[][..]),
+            None => (true, all_gamepads.as_slice()),
         };
-        let axis_rx = GamepadAxis {
-            gamepad,
-            axis_type: GamepadAxisType::RightStickX,
+        let sources = InputSources {
+            keyboard_mouse,
+            keys: &keys,
+            mouse_buttons: &mouse_buttons,
+            mouse_motion,
+            mouse_look: mouse_look.active,
+            gamepad_buttons: &gamepad_buttons,
+            gamepad_axes: &gamepad_axes,
+            gamepads,
         };
-        let axis_ry = GamepadAxis {
-            gamepad,
-            axis_type: GamepadAxisType::RightStickY,
+        *actions = if rebinding {
+            ActionState::default()
+        } else {
+            bindings.resolve(&sources)
         };
-        if let (Some(x), Some(y)) = (axes.get(axis_lx), axes.get(axis_ly)) {
-            // combine X and Y into one vector
-            let left_stick_pos = Vec2::new(x, y);
-            //info!("{:?} LeftStickX value is {}", gamepad, left_stick_pos);
-
-            // Example: check if the stick is pushed up
-            if left_stick_pos.length() > 0.3 {
-                for (_, mut vmi) in character_control.iter_mut() {
-                    vmi.move_input = left_stick_pos.normalize_or_zero();
-                }
-            }
+
+        if actions.move_input.length() > 0.0 {
+            vmi.move_input = actions.move_input;
         }
-        if let (Some(rx), Some(ry)) = (axes.get(axis_rx), axes.get(axis_ry)) {
-            if f32::abs(ry) > 0.1 {
-                for mut c in &mut camera_targets {
-                    //info!("adjusting camera pitch");
-                    c.pitch -= ry * 0.007
-                }
-            }
-            if f32::abs(rx) > 0.1 {
-                for mut c in &mut camera_targets {
-                    //info!("adjusting camera yaw");
-                    c.yaw -= rx * 0.01
-                }
-            }
+        control.jump_pressed = actions.jump.pressed;
+        if actions.jump.just_pressed {
+            control.jump_press_latched = true;
         }
-        // In a real game, the buttons would be configurable, but here we hardcode them
-        let jump_button = GamepadButton {
-            gamepad,
-            button_type: GamepadButtonType::South,
-        };
-        let heal_button = GamepadButton {
-            gamepad,
-            button_type: GamepadButtonType::East,
-        };
+        control.crouch_pressed = actions.crouch.pressed;
 
-        // If jump was pressed and is now released, update state
-        for (mut pcc, _) in character_control.iter_mut() {
-            if pcc.jump_pressed && !buttons.pressed(jump_button) {
-                pcc.jump_pressed = false;
-            }
-        }
-        // If jump was just pressed, update state
-        if buttons.just_pressed(jump_button) {
-            // button just pressed: make the player jump
-            for (mut pcc, _) in character_control.iter_mut() {
-                pcc.jump_pressed = true;
+        if let Some(mut camera_target) = camera_target {
+            camera_target.yaw -= actions.look.x * LOOK_YAW_SPEED * delta;
+            camera_target.pitch -= actions.look.y * LOOK_PITCH_SPEED * delta;
+            if actions.camera_reset.just_pressed {
+                // Swing around behind the way the character is facing.
+                let facing = control.facing_2d;
+                camera_target.yaw = f32::atan2(facing.y, -facing.x);
+                camera_target.pitch = 0.0;
             }
         }
     }
 }
+
+/// Grabs the cursor for mouse look when the window is clicked, and lets it go on Escape. Mouse
+/// look pauses while egui wants the pointer, so the inspector can still be used. While a
+/// [`PendingRebind`] is waiting, Escape only cancels the rebind, so this has to run before
+/// `capture_rebind` removes it.
+pub fn toggle_mouse_look(
+    mut mouse_look: ResMut<MouseLook>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    pending_rebind: Option<Res<PendingRebind>>,
+    mut egui: EguiContexts,
+) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    let egui_wants_pointer = egui.ctx_mut().wants_pointer_input();
+
+    let grabbed = window.cursor.grab_mode != CursorGrabMode::None;
+    if !grabbed && mouse_buttons.just_pressed(MouseButton::Left) && !egui_wants_pointer {
+        window.cursor.grab_mode = MOUSE_LOOK_GRAB_MODE;
+        window.cursor.visible = false;
+    } else if grabbed && keys.just_pressed(KeyCode::Escape) && pending_rebind.is_none() {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
+
+    let active = window.cursor.grab_mode != CursorGrabMode::None && !egui_wants_pointer;
+    if mouse_look.active != active {
+        mouse_look.active = active;
+    }
+}
+
+/// Binds the next key or button pressed to the action in [`PendingRebind`].
+pub fn capture_rebind(
+    mut commands: Commands,
+    pending: Res<PendingRebind>,
+    mut bindings: ResMut<InputBindings>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        info!("cancelled rebinding {:?}", pending.0);
+        commands.remove_resource::<PendingRebind>();
+        return;
+    }
+
+    let gamepads: Vec<Gamepad> = gamepads.iter().collect();
+    let sources = InputSources {
+        keyboard_mouse: true,
+        keys: &keys,
+        mouse_buttons: &mouse_buttons,
+        mouse_motion: Vec2::ZERO,
+        mouse_look: false,
+        gamepad_buttons: &gamepad_buttons,
+        gamepad_axes: &gamepad_axes,
+        gamepads: &gamepads,
+    };
+    let Some(binding) = Binding::just_pressed(&sources) else {
+        return;
+    };
+
+    if bindings.rebind(pending.0, binding) {
+        info!("bound {:?} to {:?}", binding, pending.0);
+        save_bindings(&bindings);
+    } else {
+        warn!(
+            "{:?} can't be bound to {:?}, edit its bindings in the inspector instead",
+            pending.0, binding
+        );
+    }
+    commands.remove_resource::<PendingRebind>();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_bindings(bindings: &InputBindings) {
+    if let Err(e) = bindings.save(BINDINGS_PATH) {
+        warn!("failed to save bindings to {}: {:?}", BINDINGS_PATH, e);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_bindings(_bindings: &InputBindings) {
+    warn!("can't save bindings on the web, they'll be reset on reload");
+}
@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+
+use crate::components::player::{
+    homing::{HomingAttack, HomingTargetEvent, HomingTargetable},
+    physics::{
+        AirSpeed, PlatformingCharacterControl, PlatformingCharacterPhysics,
+        PlatformingCharacterValues,
+    },
+    sensors::MyCollisionLayers,
+};
+
+/// Picks the nearest target in a cone in front of each airborne character, with nothing in the
+/// way.
+pub fn acquire_homing_targets(
+    mut characters: Query<(
+        Entity,
+        &mut HomingAttack,
+        &PlatformingCharacterPhysics,
+        &PlatformingCharacterControl,
+        &PlatformingCharacterValues,
+        &Position,
+    )>,
+    targets: Query<&Position, With<HomingTargetable>>,
+    spatial_query: SpatialQuery,
+    mut events: EventWriter<HomingTargetEvent>,
+) {
+    for (character, mut homing, physics, control, values, position) in characters.iter_mut() {
+        if homing.dashing_to.is_some() {
+            // Stay locked on to what we're dashing at.
+            continue;
+        }
+
+        let target = match physics.air_speed {
+            AirSpeed::Grounded { .. } => None,
+            AirSpeed::InAir(_) => {
+                // The facing is the input after it was mapped through the camera, which in the
+                // air is across the plane square to gravity.
+                let facing = values.air_rotation()
+                    * Vec3::new(control.facing_2d.x, 0.0, control.facing_2d.y)
+                        .try_normalize()
+                        .unwrap_or(Vec3::X);
+                let min_cos = values.homing_cone_angle.cos();
+
+                spatial_query
+                    .shape_intersections(
+                        &Collider::ball(values.homing_range),
+                        position.0,
+                        Quat::IDENTITY,
+                        SpatialQueryFilter::new().with_masks([MyCollisionLayers::Enemy]),
+                    )
+                    .into_iter()
+                    .filter_map(|entity| {
+                        let target_position = targets.get(entity).ok()?;
+                        let offset = target_position.0 - position.0;
+                        let distance = offset.length();
+                        if distance > values.homing_range
+                            || offset.normalize_or_zero().dot(facing) < min_cos
+                        {
+                            return None;
+                        }
+                        // Don't lock on through walls.
+                        let blocked = spatial_query
+                            .cast_ray(
+                                position.0,
+                                offset / distance,
+                                distance,
+                                true,
+                                SpatialQueryFilter::new()
+                                    .with_masks([MyCollisionLayers::Environment]),
+                            )
+                            .is_some();
+                        (!blocked).then_some((entity, distance))
+                    })
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(entity, _)| entity)
+            }
+        };
+
+        if target != homing.target {
+            if let Some(lost) = homing.target {
+                events.send(HomingTargetEvent::Lost {
+                    character,
+                    target: lost,
+                });
+            }
+            if let Some(acquired) = target {
+                events.send(HomingTargetEvent::Acquired {
+                    character,
+                    target: acquired,
+                });
+            }
+            homing.target = target;
+        }
+    }
+}
+
+/// Steers characters that are homing in on a target straight at it, and bounces them off it
+/// when they get there.
+pub fn update_homing_dash(
+    mut characters: Query<(
+        Entity,
+        &mut HomingAttack,
+        &mut PlatformingCharacterPhysics,
+        &PlatformingCharacterValues,
+        &Position,
+    )>,
+    targets: Query<&Position, With<HomingTargetable>>,
+    spatial_query: SpatialQuery,
+    time: Res<Time>,
+    mut events: EventWriter<HomingTargetEvent>,
+) {
+    for (character, mut homing, mut physics, values, position) in characters.iter_mut() {
+        let Some(target) = homing.dashing_to else {
+            continue;
+        };
+        homing.dash_time += time.delta_seconds();
+
+        let target_position = match targets.get(target) {
+            Ok(target_position) => target_position.0,
+            // The target went away.
+            Err(_) => {
+                homing.dashing_to = None;
+                continue;
+            }
+        };
+        let grounded = matches!(physics.air_speed, AirSpeed::Grounded { .. });
+        if grounded || homing.dash_time > values.homing_max_dash_time {
+            info!("homing attack missed");
+            homing.dashing_to = None;
+            continue;
+        }
+
+        let (cushion_radius, _) = values.body_radii(physics.rolling);
+        let touching = spatial_query
+            .shape_intersections(
+                &Collider::ball(cushion_radius),
+                position.0,
+                Quat::IDENTITY,
+                SpatialQueryFilter::new().with_masks([MyCollisionLayers::Enemy]),
+            )
+            .contains(&target);
+
+        if touching {
+            info!("homing attack hit {:?}", target);
+            events.send(HomingTargetEvent::Hit { character, target });
+            homing.dashing_to = None;
+            physics.air_speed = AirSpeed::InAir(values.up() * values.homing_bounce_speed);
+            physics.jumping = false;
+        } else {
+            let direction = (target_position - position.0).normalize_or_zero();
+            physics.air_speed = AirSpeed::InAir(direction * values.homing_speed);
+        }
+    }
+}
+
+/// Draws a reticle around each character's homing target.
+pub fn draw_homing_reticle(
+    characters: Query<(&HomingAttack, &GlobalTransform)>,
+    targets: Query<&GlobalTransform, With<HomingTargetable>>,
+    mut gizmos: Gizmos,
+) {
+    for (homing, character_transform) in characters.iter() {
+        let Some(target) = homing.dashing_to.or(homing.target) else {
+            continue;
+        };
+        let Ok(target_transform) = targets.get(target) else {
+            continue;
+        };
+        let target_position = target_transform.translation();
+        // Face the reticle towards the character.
+        let normal = (character_transform.translation() - target_position)
+            .try_normalize()
+            .unwrap_or(Vec3::Y);
+        let color = if homing.dashing_to.is_some() {
+            Color::RED
+        } else {
+            Color::YELLOW
+        };
+        gizmos.circle(target_position, normal, 0.75, color);
+        gizmos.circle(target_position, normal, 0.5, color);
+    }
+}
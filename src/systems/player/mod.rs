@@ -1,5 +1,7 @@
 pub mod animation;
 pub mod control;
+pub mod homing;
 pub mod physics;
+pub mod replay;
 pub mod sensors;
 pub mod spawn;
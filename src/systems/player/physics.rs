@@ -1,34 +1,113 @@
 use std::{
-    f32::{consts::PI, EPSILON},
+    f32::consts::{FRAC_1_SQRT_2, PI},
     ops::Mul,
 };
 
-use bevy::{input::mouse::MouseMotion, prelude::*};
+use bevy::prelude::*;
 use bevy_xpbd_3d::{math::Scalar, prelude::*};
 
-use crate::components::{
-    camera::{OrbitCameraTarget, ViewpointMappedInput},
-    player::{
-        physics::{
-            AirSpeed, FloorInfo, KinematicCharacterPhysics, PlatformingCharacterAnimationFlags,
-            PlatformingCharacterControl, PlatformingCharacterPhysics,
-            PlatformingCharacterPhysicsAccel, PlatformingCharacterValues,
-        },
-        sensors::{CharacterSensor, CharacterSensorArray, MyCollisionLayers},
+use crate::components::player::{
+    animation::Animated,
+    homing::HomingAttack,
+    physics::{
+        AirSpeed, ChargeDash, FloorInfo, PlatformingCharacterAnimationFlags,
+        PlatformingCharacterControl, PlatformingCharacterInterpolation,
+        PlatformingCharacterPhysics, PlatformingCharacterPhysicsAccel, PlatformingCharacterValues,
     },
+    sensors::{CharacterSensor, CharacterSensorArray, MyCollisionLayers},
 };
 
 pub fn update_platforming_accel_from_controls(
     mut query: Query<(
         &mut PlatformingCharacterPhysicsAccel,
-        &PlatformingCharacterPhysics,
+        &mut PlatformingCharacterPhysics,
         &mut PlatformingCharacterControl,
         &PlatformingCharacterValues,
         &mut PlatformingCharacterAnimationFlags,
+        &mut Collider,
+        // Physics adds this in the first step, after the first tick's controls.
+        Option<&Position>,
+        Option<&mut HomingAttack>,
     )>,
+    time: Res<Time>,
+    // `SpatialQuery` reads every `Collider`, which would conflict with swapping the character's.
+    spatial_query: Res<SpatialQueryPipeline>,
 ) {
-    for (mut accel, platforming, mut control, values, mut animation_flags) in query.iter_mut() {
-        if control.move_input.length() > 0.0 {
+    let delta = time.delta_seconds();
+    for (
+        mut accel,
+        mut platforming,
+        mut control,
+        values,
+        mut animation_flags,
+        mut collider,
+        position,
+        homing,
+    ) in query.iter_mut()
+    {
+        // The latch catches taps that were pressed and released between two ticks.
+        let jump_just_pressed =
+            (control.jump_pressed && !accel.jump_pressed_last_tick) || control.jump_press_latched;
+        accel.jump_pressed_last_tick = control.jump_pressed;
+        control.jump_press_latched = false;
+        let crouch_just_pressed = control.crouch_pressed && !accel.crouch_pressed_last_tick;
+        accel.crouch_pressed_last_tick = control.crouch_pressed;
+
+        let was_rolling = platforming.rolling;
+        let room_to_stand = |platforming: &PlatformingCharacterPhysics| {
+            // Standing up grows the ball upwards from where its bottom touches the ground.
+            let (_, rolling_radius) = values.body_radii(true);
+            let (_, standing_radius) = values.body_radii(false);
+            let growth = standing_radius - rolling_radius;
+            let Some(position) = position else {
+                return true;
+            };
+            spatial_query
+                .cast_shape(
+                    &Collider::ball(standing_radius),
+                    position.0,
+                    Quat::IDENTITY,
+                    platforming.ground_cast_direction * -1.0,
+                    growth,
+                    false,
+                    SpatialQueryFilter::new().with_masks([MyCollisionLayers::Environment]),
+                )
+                .is_none()
+        };
+        update_rolling(&mut platforming, values, crouch_just_pressed, room_to_stand);
+        let charging =
+            update_charge_dash(&mut platforming, &control, values, jump_just_pressed, delta);
+        if platforming.rolling != was_rolling {
+            let (_, radius) = values.body_radii(platforming.rolling);
+            *collider = Collider::ball(radius);
+        }
+        let grounded = matches!(platforming.air_speed, AirSpeed::Grounded { .. });
+
+        if charging {
+            // Can't run while charging, but the input still aims the dash.
+            if let Some(aim) = control.move_input.try_normalize() {
+                control.facing_2d = aim;
+                platforming.ground_direction = aim;
+            }
+            accel.ground_acceleration = Vec2::ZERO;
+            accel.ground_friction = values.friction_speed;
+        } else if platforming.rolling && grounded {
+            // Input can't speed up a roll, only brake against it, unless the roll is only going on
+            // because there's no room to stand; then it can nudge the character out.
+            let braking = control.move_input.length() > 0.0
+                && platforming
+                    .ground_speed
+                    .angle_between(control.move_input)
+                    .abs()
+                    > PI / 2.0;
+            let cramped = platforming.ground_speed.length() < values.roll_stop_speed;
+            accel.ground_acceleration = if braking || cramped {
+                values.roll_deceleration_speed * control.move_input
+            } else {
+                Vec2::ZERO
+            };
+            accel.ground_friction = values.roll_friction_speed;
+        } else if control.move_input.length() > 0.0 {
             control.facing_2d = control.move_input;
             // Moving in a direction.
             let mut accel_amount = match platforming.air_speed {
@@ -37,9 +116,10 @@ pub fn update_platforming_accel_from_controls(
             };
             // If moving in a direction opposite the player's ground speed, apply deceleration
             // speed too.
-            if platforming.ground_speed.length() > 0.0 {
+            let planar_velocity = platforming.planar_velocity();
+            if planar_velocity.length() > 0.0 {
                 let angle_between_input_and_speed =
-                    platforming.ground_speed.angle_between(control.move_input);
+                    planar_velocity.angle_between(control.move_input);
                 if angle_between_input_and_speed.abs() > PI / 2.0 {
                     accel_amount += values.deceleration_speed;
                     animation_flags.skidding = true;
@@ -49,83 +129,227 @@ pub fn update_platforming_accel_from_controls(
             }
             accel.ground_acceleration = accel_amount * control.move_input;
             accel.ground_friction = 0.0;
-
-            // Consume the input
-            control.move_input = Vec2::ZERO;
+            // Don't consume the input: the input systems overwrite it every frame, and a slow
+            // frame can run several fixed ticks that should all see it.
         } else {
             accel.ground_acceleration = Vec2::ZERO;
             accel.ground_friction = values.friction_speed;
         }
 
-        match (&platforming.air_speed, control.jump_pressed) {
-            (AirSpeed::Grounded { .. }, true) => {
-                accel.air_acceleration = values.jump_speed;
+        // Jumping takes a fresh press, so holding jump doesn't bounce. The press is buffered for a
+        // little while, so pressing jump just before landing still jumps on touchdown.
+        accel.jump_buffer_left = if jump_just_pressed {
+            values.jump_buffer_time
+        } else {
+            (accel.jump_buffer_left - delta).max(0.0)
+        };
+        if charging {
+            // The presses that rev up a charge dash don't jump.
+            accel.jump_buffer_left = 0.0;
+        }
+        // Just after running off a ledge, a jump still counts.
+        let can_jump = grounded || platforming.coyote_time_left > 0.0;
+
+        accel.jump = false;
+        match (platforming.air_speed, jump_just_pressed) {
+            _ if can_jump && accel.jump_buffer_left > 0.0 => {
+                accel.jump = true;
+                accel.jump_buffer_left = 0.0;
+            }
+            // Pressing jump again in midair dashes at the homing target, if there is one.
+            (AirSpeed::InAir(_), true) => {
+                if let Some(mut homing) = homing {
+                    if let Some(target) = homing.target {
+                        info!("homing attack on {:?}", target);
+                        homing.dashing_to = Some(target);
+                        homing.dash_time = 0.0;
+                        // The press went to the homing attack, so don't jump on landing too.
+                        accel.jump_buffer_left = 0.0;
+                    }
+                }
             }
-            (AirSpeed::InAir(_), false) => {
-                accel.air_acceleration = 0.0; // TODO: this blocks any contribution to air accel
-                                              // other than jumping.
+            (AirSpeed::InAir(velocity), _) if !control.jump_pressed && platforming.jumping => {
+                // Letting go of jump on the way up cuts the jump short, for short hops.
+                let up = values.up();
+                let rising_speed = velocity.dot(up);
+                if rising_speed > values.jump_release_speed {
+                    let cut = rising_speed - values.jump_release_speed;
+                    platforming.air_speed = AirSpeed::InAir(velocity - up * cut);
+                }
+                platforming.jumping = false;
             }
             _ => (),
         }
     }
 }
 
+/// Starts rolling when crouch is pressed while moving on the ground, and stops once the roll has
+/// slowed down and there's room to stand.
+fn update_rolling(
+    platforming: &mut PlatformingCharacterPhysics,
+    values: &PlatformingCharacterValues,
+    crouch_just_pressed: bool,
+    room_to_stand: impl FnOnce(&PlatformingCharacterPhysics) -> bool,
+) {
+    let AirSpeed::Grounded { .. } = platforming.air_speed else {
+        // Keep rolling through the air, and work it out again on landing.
+        return;
+    };
+    let speed = platforming.ground_speed.length();
+    if platforming.rolling {
+        // Keep rolling under a low ceiling until there's room to stand back up.
+        if speed < values.roll_stop_speed && room_to_stand(platforming) {
+            info!("stopped rolling");
+            platforming.rolling = false;
+        }
+    } else if crouch_just_pressed && speed > values.roll_start_speed {
+        info!("started rolling");
+        platforming.rolling = true;
+    }
+}
+
+/// Runs the charge dash: crouching while standing still and pressing jump revs up a charge, and
+/// letting go of crouch launches the character along its ground direction. Returns whether the
+/// character is still charging.
+fn update_charge_dash(
+    platforming: &mut PlatformingCharacterPhysics,
+    control: &PlatformingCharacterControl,
+    values: &PlatformingCharacterValues,
+    jump_just_pressed: bool,
+    delta: f32,
+) -> bool {
+    let grounded = matches!(platforming.air_speed, AirSpeed::Grounded { .. });
+    match platforming.charge_dash {
+        ChargeDash::Idle => {
+            // Same threshold the kinematic step uses for whether we're moving.
+            let stationary = platforming.ground_speed.length() <= 1.0;
+            if grounded && stationary && control.crouch_pressed && jump_just_pressed {
+                info!("charging dash");
+                platforming.charge_dash = ChargeDash::Charging {
+                    charge: values.charge_dash_rev,
+                };
+            }
+        }
+        ChargeDash::Charging { charge } => {
+            if !grounded {
+                platforming.charge_dash = ChargeDash::Idle;
+            } else if !control.crouch_pressed {
+                let speed =
+                    values.charge_dash_base_speed + charge * values.charge_dash_speed_per_charge;
+                info!("charge dash released at {:?}", speed);
+                platforming.ground_speed = platforming.ground_direction * speed;
+                platforming.charge_dash = ChargeDash::Idle;
+                // The dash comes out as a roll.
+                platforming.rolling = true;
+            } else {
+                // The charge drains away, so it has to be kept up by pressing jump.
+                let mut charge = charge * (-values.charge_dash_decay * delta).exp();
+                if jump_just_pressed {
+                    charge = (charge + values.charge_dash_rev).min(values.charge_dash_max_charge);
+                }
+                platforming.charge_dash = ChargeDash::Charging { charge };
+            }
+        }
+    }
+    matches!(platforming.charge_dash, ChargeDash::Charging { .. })
+}
+
 pub fn update_platforming_physics(
     mut query: Query<(
         &mut PlatformingCharacterPhysics,
         &mut PlatformingCharacterPhysicsAccel,
         &PlatformingCharacterValues,
     )>,
+    time: Res<Time>,
 ) {
-    for (mut platforming, mut accel, values) in query.iter_mut() {
-        if accel.air_acceleration > 0.0 {
-            if let AirSpeed::Grounded { .. } = platforming.air_speed {
-                // Trying to jump, and on the ground.
-                platforming.air_speed = AirSpeed::InAir(accel.air_acceleration);
+    // This runs in FixedUpdate, so this is the fixed timestep.
+    let delta = time.delta_seconds();
+    for (mut platforming, accel, values) in query.iter_mut() {
+        if accel.jump {
+            match platforming.air_speed {
+                AirSpeed::Grounded { .. } => {
+                    // Trying to jump, and on the ground. Push off away from the surface, whether
+                    // that's the floor, a wall or a ceiling, and keep the speed we had along it.
+                    let surface_up = platforming.ground_cast_direction * -1.0;
+                    let along_surface = platforming.slope_direction
+                        * platforming.ground_speed.dot(platforming.ground_direction);
+                    platforming
+                        .leave_ground(along_surface + surface_up * values.jump_speed, values);
+                    platforming.jumping = true;
+                }
+                AirSpeed::InAir(velocity) if platforming.coyote_time_left > 0.0 => {
+                    // Just ran off a ledge. Jump as if we were still on it.
+                    let up = values.up();
+                    let along_ground = velocity - up * velocity.dot(up);
+                    platforming.leave_ground(along_ground + up * values.jump_speed, values);
+                    platforming.jumping = true;
+                }
+                AirSpeed::InAir(_) => {}
             }
         }
+        platforming.coyote_time_left = (platforming.coyote_time_left - delta).max(0.0);
 
-        let ground_accel = match platforming.air_speed {
+        let _ground_accel = match platforming.air_speed {
             AirSpeed::Grounded { .. } => accel.ground_acceleration,
             AirSpeed::InAir(_) => accel.ground_acceleration * 0.5,
         };
-        //let initial_speed = platforming.ground_speed.length() > values.top_speed;
-        // Apply acceleration if we aren't over top speed.
-        platforming.ground_speed += accel.ground_acceleration;
-        // Actually for now just clamp ground speed to top speed. tune it later.
-        platforming.ground_speed = platforming.ground_speed.clamp_length(0.0, values.top_speed);
 
         match platforming.air_speed {
             AirSpeed::Grounded { .. } => {
+                // Apply acceleration if we aren't over top speed. Slopes can take us over it, and
+                // acceleration shouldn't take that away.
+                let speed_limit = values.top_speed.max(platforming.ground_speed.length());
+                platforming.ground_speed += accel.ground_acceleration * delta;
+                platforming.ground_speed = platforming.ground_speed.clamp_length(0.0, speed_limit);
+
+                // Gravity pulls the character along the slope. The slope direction is where
+                // we're heading along the surface, so how far it points up is how steeply that's
+                // uphill.
+                let up = values.up();
+                let incline = platforming.slope_direction.dot(up);
+                let slope_factor = values.slope_factor(platforming.rolling, incline > 0.0);
+                let ground_direction = platforming.ground_direction;
+                platforming.ground_speed -= ground_direction * (slope_factor * incline * delta);
+
+                // Too slow to stay on a wall or ceiling, so fall off.
+                let surface_up = platforming.overall_rotation.mul_vec3(Vec3::Y);
+                let speed = platforming.ground_speed.length();
+                if surface_up.dot(up) < FRAC_1_SQRT_2 && speed < values.min_stick_speed {
+                    debug!("too slow to stick to the surface");
+                    let velocity = platforming.slope_direction * speed;
+                    platforming.leave_ground(velocity, values);
+                    continue;
+                }
+
                 // Apply friction
-                if (accel.ground_friction > 0.0) {
-                    // Get friction vector - start with a unit vector that's facing the direction
-                    // of ground speed.
-                    let ground_friction_direction = platforming.ground_speed.normalize_or_zero();
-                    // flip it
-                    let ground_friction_direction = Vec2 {
-                        x: ground_friction_direction.x * -1.0,
-                        y: ground_friction_direction.y * -1.0,
-                    };
-                    // multiply it by friction_speed
-                    let ground_friction = accel.ground_friction * ground_friction_direction;
-                    // add the friction vector to the ground speed.
-                    platforming.ground_speed += ground_friction;
-
-                    // if the ground speed is now facing the same direction as the friction vector was,
-                    // we should stop.
-                    if platforming.ground_speed.normalize_or_zero() == ground_friction_direction {
-                        platforming.ground_speed = Vec2::ZERO;
-                    }
+                if accel.ground_friction > 0.0 {
+                    // Friction slows the character down along the direction it's moving in,
+                    // but never far enough to push it backwards.
+                    let speed = platforming.ground_speed.length();
+                    let slowed_speed = (speed - accel.ground_friction * delta).max(0.0);
+                    platforming.ground_speed =
+                        platforming.ground_speed.normalize_or_zero() * slowed_speed;
                 }
             }
-            AirSpeed::InAir(air_speed) => {
-                // Apply acceleration and gravity
-                let air_speed = air_speed + accel.air_acceleration + values.gravity;
-                // TODO: consider separate top speed for air.
-                let air_speed = air_speed.clamp(values.top_speed * -1.0, values.top_speed);
+            AirSpeed::InAir(velocity) => {
+                // Input steers the part of the velocity across the plane square to gravity,
+                // under the same limit as on the ground.
+                let air_rotation = values.air_rotation();
+                let local_velocity = air_rotation.inverse().mul_vec3(velocity);
+                let across = local_velocity.xz();
+                let speed_limit = values.top_speed.max(across.length());
+                let across =
+                    (across + accel.ground_acceleration * delta).clamp_length(0.0, speed_limit);
+                let velocity =
+                    air_rotation.mul_vec3(Vec3::new(across.x, local_velocity.y, across.y));
 
-                platforming.air_speed = AirSpeed::InAir(air_speed);
+                // Apply gravity and drag, and cap the speed. Both scale the whole velocity, so a
+                // launch keeps heading exactly where it was going.
+                let drag = (-values.air_drag * delta).exp();
+                let velocity = (velocity + values.gravity * delta) * drag;
+                let velocity = velocity.clamp_length_max(values.air_top_speed);
+
+                platforming.air_speed = AirSpeed::InAir(velocity);
             }
         }
 
@@ -143,28 +367,20 @@ pub fn update_platforming_kinematic_from_physics(
         &Rotation,
         &mut Transform,
         &FloorInfo,
-        &GlobalTransform,
+        &Position,
         &PlatformingCharacterControl,
         &PlatformingCharacterValues,
     )>,
     mut gizmos: Gizmos,
     spatial_query: SpatialQuery,
 ) {
-    for (
-        mut physics,
-        rb,
-        mut lv,
-        rot,
-        mut transform,
-        floor_info,
-        global_transform,
-        control,
-        values,
-    ) in query.iter_mut()
+    for (mut physics, _rb, mut lv, _rot, _transform, _floor_info, position, _control, values) in
+        query.iter_mut()
     {
         let show_gizmos = physics.show_gizmos;
-        if physics.ground_speed.length() > 1.0 {
-            physics.ground_direction = physics.ground_speed.normalize();
+        let planar_velocity = physics.planar_velocity();
+        if planar_velocity.length() > 1.0 {
+            physics.ground_direction = planar_velocity.normalize();
         }
         // Map the ground direction into 3d space
         let mut direction = Vec3 {
@@ -175,68 +391,13 @@ pub fn update_platforming_kinematic_from_physics(
 
         let mut overall_character_rotation = Quat::from_rotation_arc(Vec3::Z, direction);
 
-        // Wall running
-        if let AirSpeed::Grounded { angle, .. } = physics.air_speed {
-            if (angle > PI / 4.0 || angle < -PI / 4.0) {
-                // Cast a ray in the direction we are trying to go. If it hits something, use it as a new ground cast direction
-
-                if let Some(running_up_wall_cast) = spatial_query.cast_ray(
-                    global_transform.translation(),
-                    lv.0,
-                    1.0,
-                    true,
-                    SpatialQueryFilter::new().with_masks([MyCollisionLayers::Environment]),
-                ) {
-                    let mut new_ground_direction = Vec3::ZERO - running_up_wall_cast.normal;
-                    if (physics.wall_running) {
-                        // Leaving a wall. Keep only Y
-                        new_ground_direction.x = 0.0;
-                        new_ground_direction.z = 0.0;
-                        // Positive Y is ceiling running.
-                        if new_ground_direction.y > 0.0 {
-                            new_ground_direction.y = 1.0;
-                            // Get ceiling run axis
-                            // 90 degrees from the current linear velocity direction, perpendicular to the wall we're walking on
-                            let wall_to_ceil_arc = Quat::from_rotation_arc(physics.ground_cast_direction, new_ground_direction);
-                            let floor_to_wall_arc = Quat::from_rotation_arc(Vec3::NEG_Y, physics.ground_cast_direction);
-
-                            physics.ceiling_run_quat = Some(floor_to_wall_arc.mul_quat(wall_to_ceil_arc))
-                        } else {
-                            new_ground_direction.y = -1.0;
-                            physics.ceiling_run_quat = None;
-                        }
-                        physics.wall_running = false;
-                    } else {
-                        // Remove y component. Only want x/z (unless we are doing ceiling running)
-                        new_ground_direction.y = 0.0;
-                        if let Some(n) = new_ground_direction.try_normalize() {
-                            new_ground_direction = n;
-                            physics.wall_running = true;
-                            physics.ceiling_run_quat = None;
-                        } else {
-                            // Return to default (ground is down)
-                            new_ground_direction = Vec3::NEG_Y;
-                            physics.wall_running = false;
-                            physics.ceiling_run_quat = None;
-                        }
-                    }
-                    physics.ground_cast_direction = new_ground_direction;
-                }
-            }
-        }
-
-        // Get a quat representing the rotation between down (neg Y) and whatever the ground is right now.
-        // If ceiling running, use the stored quat, which is oriented to the wall that was climbed before the ceiling run.
-        let cast_origin_rotation = match physics.ceiling_run_quat {
-            Some(c) => c,
-            None => Quat::from_rotation_arc(Vec3::NEG_Y, physics.ground_cast_direction)
-        };
+        // The surface we're running on, as a rotation from flat ground. It follows the surface
+        // normal continuously, so it can take us up walls, across ceilings, and around loops.
+        let cast_origin_rotation = physics.surface_rotation;
 
         direction = cast_origin_rotation.mul(direction);
         overall_character_rotation = cast_origin_rotation * overall_character_rotation;
 
-        let mut colliding_with_wall = false;
-
         if let Some(collision_normal) = physics.wall_collision_normal {
             // Map the wall collision normal (it's the normal from the character, so it is pointing into the wall)
             // into 2d space
@@ -259,37 +420,43 @@ pub fn update_platforming_kinematic_from_physics(
                 // Adjust the current ground speed by that.
                 // We multiply the existing one so that the orientation is not affected (which a subtraction would do)
                 info!("adjusted ground speed {:?}", physics.ground_speed);
-                colliding_with_wall = true;
             }
             // ray_arrow_gizmo(
             //     &mut gizmos,
-            //     global_transform.translation(),
+            //     position.0,
             //     wall_collision_normal_2d,
             //     Color::WHITE,
             // );
 
             // ray_arrow_gizmo(
             //     &mut gizmos,
-            //     global_transform.translation(),
+            //     position.0,
             //     direction,
             //     Color::RED,
             // );
+            // In the air, stop moving into the wall.
+            if let AirSpeed::InAir(ref mut velocity) = physics.air_speed {
+                let into_wall_speed = velocity.dot(collision_normal);
+                if into_wall_speed > 0.0 {
+                    *velocity -= collision_normal * into_wall_speed;
+                }
+            }
             physics.wall_collision_normal = None;
         }
 
         // Radius for slope detection, and the amount of distance we want to have from the ground. it's a 'cushion' around the actual collider.
-        let radius = values.cushion_radius;
+        // How big our radius for bonking into stuff is.
+        // Both shrink while rolling.
+        let (radius, obstacle_detection_radius) = values.body_radii(physics.rolling);
         // How big our 'footprint' is.
         let ground_detection_radius = values.ground_detection_radius;
-        // How big our radius for bonking into stuff is.
-        let obstacle_detection_radius = values.obstacle_detection_radius;
 
         // Check if we're in bonking range for any obstacles (walls)
         let obstacle_cast_distance = radius - obstacle_detection_radius;
 
         let obstacle = match spatial_query.cast_shape(
             &Collider::ball(obstacle_detection_radius),
-            global_transform.translation(),
+            position.0,
             Quat::default(),
             direction,
             obstacle_cast_distance,
@@ -331,15 +498,13 @@ pub fn update_platforming_kinematic_from_physics(
         } else {
             radius
         };
-        let mut desired_distance_from_ground = radius - ground_detection_radius;
+        let desired_distance_from_ground = radius - ground_detection_radius;
         let ground_cast_overshoot = 0.1;
         let mut ground_cast_direction = slope_cast_direction; // may be adjusted based on detected slope
-        let slope_cast_translate = (slope_cast_direction * radius) * -1.0;
-        let front_slope_cast_origin =
-            global_transform.translation() + (direction * (slope_cast_spacing));
-        let back_slope_cast_origin =
-            global_transform.translation() + (direction * (slope_cast_spacing * -1.0));
-        let ground_cast_origin = global_transform.translation();
+        let _slope_cast_translate = (slope_cast_direction * radius) * -1.0;
+        let front_slope_cast_origin = position.0 + (direction * (slope_cast_spacing));
+        let back_slope_cast_origin = position.0 + (direction * -slope_cast_spacing);
+        let ground_cast_origin = position.0;
         let mut ground_cast_length = desired_distance_from_ground; // Set this using the longer slope cast, if there is one. but start with the desired distance from ground
         let front_slope_cast = spatial_query.cast_ray(
             front_slope_cast_origin,
@@ -357,12 +522,7 @@ pub fn update_platforming_kinematic_from_physics(
         );
 
         if show_gizmos {
-            gizmos.sphere(
-                global_transform.translation(),
-                Quat::default(),
-                radius,
-                Color::BLACK,
-            );
+            gizmos.sphere(position.0, Quat::default(), radius, Color::BLACK);
 
             gizmos.ray(
                 front_slope_cast_origin,
@@ -376,7 +536,9 @@ pub fn update_platforming_kinematic_from_physics(
             );
         }
 
-        let mut slope_detected = false;
+        // Normal of the surface under the slope casts, and how sharply it curves.
+        let mut surface_normal = None;
+        let mut surface_curvature = 0.0;
 
         match (front_slope_cast, back_slope_cast) {
             (Some(front), Some(back)) => {
@@ -386,6 +548,21 @@ pub fn update_platforming_kinematic_from_physics(
                 let back_contact =
                     back_slope_cast_origin + (slope_cast_direction * back.time_of_impact);
 
+                surface_normal = (front.normal + back.normal).try_normalize();
+                // Curvature is how much the normal turns per unit of distance between the
+                // contacts. It's positive when the surface curves up towards us, like the inside
+                // of a loop, and negative over the top of a hill.
+                let contact_span = front_contact - back_contact;
+                let contact_distance = contact_span.length();
+                if contact_distance > f32::EPSILON {
+                    let bend = front.normal.angle_between(back.normal) / contact_distance;
+                    surface_curvature = if (front.normal - back.normal).dot(contact_span) < 0.0 {
+                        bend
+                    } else {
+                        -bend
+                    };
+                }
+
                 let slope = Vec3::normalize(front_contact - back_contact);
                 let new_slope_quat = Quat::from_rotation_arc(direction, slope);
                 let sloped_direction = new_slope_quat.mul_vec3(direction);
@@ -410,7 +587,6 @@ pub fn update_platforming_kinematic_from_physics(
                 // info!("slope quat {:?}", slope_quat);
                 direction = sloped_direction;
                 ground_cast_direction = new_slope_quat.mul_vec3(ground_cast_direction);
-                slope_detected = true;
             }
             (Some(_), None) | (None, Some(_)) => {
                 // Only one sensor is making contact.
@@ -418,6 +594,28 @@ pub fn update_platforming_kinematic_from_physics(
             _ => {}
         }
 
+        // Decide whether we can keep following the surface. Gravity pulls us off of it when it's
+        // overhead, and following a curve takes centripetal acceleration. The character sticks as
+        // long as its speed around the curve, plus a bit of grip, can provide that.
+        let mut adhered = true;
+        if let (AirSpeed::Grounded { .. }, Some(normal)) = (physics.air_speed, surface_normal) {
+            let speed = physics.ground_speed.length();
+            let centripetal_acceleration = speed * speed * surface_curvature;
+            let gravity_off_surface = values.gravity.dot(normal);
+            if centripetal_acceleration + values.surface_adhesion < gravity_off_surface {
+                debug!("not fast enough to follow the surface");
+                adhered = false;
+            } else {
+                // Follow the surface on the next step. Rotating from the last cast direction by
+                // the shortest arc keeps this continuous, even upside down.
+                let new_cast_direction = normal * -1.0;
+                let surface_change =
+                    Quat::from_rotation_arc(physics.ground_cast_direction, new_cast_direction);
+                physics.surface_rotation = (surface_change * physics.surface_rotation).normalize();
+                physics.ground_cast_direction = new_cast_direction;
+            }
+        }
+
         if show_gizmos {
             gizmos.ray(
                 ground_cast_origin,
@@ -430,18 +628,23 @@ pub fn update_platforming_kinematic_from_physics(
                 Color::SEA_GREEN,
             );
         }
-        let ground_cast = spatial_query.cast_shape(
-            &Collider::ball(ground_detection_radius),
-            ground_cast_origin,
-            Quat::default(),
-            ground_cast_direction,
-            ground_cast_length + ground_cast_overshoot,
-            true,
-            SpatialQueryFilter::new().with_masks([MyCollisionLayers::Environment]),
-        );
+        // If we lost our grip on the surface, treat it like there's no ground.
+        let ground_cast = if adhered {
+            spatial_query.cast_shape(
+                &Collider::ball(ground_detection_radius),
+                ground_cast_origin,
+                Quat::default(),
+                ground_cast_direction,
+                ground_cast_length + ground_cast_overshoot,
+                true,
+                SpatialQueryFilter::new().with_masks([MyCollisionLayers::Environment]),
+            )
+        } else {
+            None
+        };
 
         // Set linear velocity
-        let mut desired_linear_velocity = direction * physics.ground_speed.length();
+        let desired_linear_velocity = direction * physics.ground_speed.length();
 
         // Check if we're on the ground or not.
         match ground_cast {
@@ -451,13 +654,16 @@ pub fn update_platforming_kinematic_from_physics(
                     // We're still on the ground.
                     AirSpeed::Grounded { .. } => {}
                     // We were in the air, and may have just landed.
-                    AirSpeed::InAir(air_speed) => {
+                    AirSpeed::InAir(velocity) => {
                         // The cast is longer than the actual distance from the ground our character should have.
                         // Check that we are actually 'touching the ground' (measured distance <= desired distance)
                         // Also make sure we aren't trying to move upward (jump). Probable TODO: Have a flag for this, so we can jump off ceilings unimpeded.
-                        if air_speed <= 0.0 && ground.time_of_impact <= desired_distance_from_ground
+                        if velocity.dot(values.up()) <= 0.0
+                            && ground.time_of_impact <= desired_distance_from_ground
                         {
                             info!("just grounded");
+                            // Land with the part of the velocity that's square to gravity.
+                            physics.ground_speed = physics.planar_velocity();
                             physics.air_speed = AirSpeed::Grounded {
                                 angle: 0.0, /* TODO: does it need to be computed here? */
                                 slope_quat: Quat::default(),
@@ -477,29 +683,22 @@ pub fn update_platforming_kinematic_from_physics(
             }
             None => {
                 if show_gizmos {
-                    gizmos.circle(
-                        global_transform.translation(),
-                        Vec3::Y,
-                        1.0,
-                        Color::ALICE_BLUE,
-                    );
+                    gizmos.circle(position.0, Vec3::Y, 1.0, Color::ALICE_BLUE);
                 }
 
                 // We aren't on the ground now. Were we previously?
                 if let AirSpeed::Grounded { .. } = physics.air_speed {
+                    // Only walking off ground that's close to level gives a moment to still jump,
+                    // not losing our grip on a wall or ceiling.
+                    let surface_up = physics.ground_cast_direction * -1.0;
+                    let walked_off = surface_up.dot(values.up()) >= FRAC_1_SQRT_2;
                     // Yes, we need to move into the 'in-air' state.
-                    // While we're in the air,
-                    // - the air speed controls the y component
-                    // - the ground speed controls the x and z components
-                    // So we need to take the current linear velocity and map it to those.
+                    // Take the current linear velocity into it.
                     // That'll let us carry our momentum from a wallrun.
-
-                    physics.air_speed = AirSpeed::InAir(lv.y); // Use the y component of the current running speed
-                    physics.ground_speed.x = lv.x;
-                    physics.ground_speed.y = lv.z;
-                    physics.wall_running = false;
-                    physics.ceiling_run_quat = None;
-                    physics.ground_cast_direction = Vec3::NEG_Y;
+                    physics.leave_ground(lv.0, values);
+                    if walked_off {
+                        physics.coyote_time_left = values.coyote_time;
+                    }
                 }
             }
         }
@@ -508,12 +707,12 @@ pub fn update_platforming_kinematic_from_physics(
         if show_gizmos {
             ray_arrow_gizmo(
                 &mut gizmos,
-                global_transform.translation(),
+                position.0,
                 desired_linear_velocity.normalize() * (radius * ground_detection_radius),
                 Color::BLUE,
             );
             gizmos.sphere(
-                global_transform.translation()
+                position.0
                     + (desired_linear_velocity.normalize() * (radius * ground_detection_radius)),
                 Quat::default(),
                 ground_detection_radius,
@@ -523,10 +722,11 @@ pub fn update_platforming_kinematic_from_physics(
 
         // Apply linear velocity.
         lv.0 = desired_linear_velocity;
-        // If we are in the air at the end of all this, set the y component of the linear velocity to the air speed.
-        if let AirSpeed::InAir(air_speed) = physics.air_speed {
-            lv.y = air_speed;
-            physics.ground_cast_direction = Vec3::NEG_Y;
+        physics.slope_direction = direction;
+        // If we are in the air at the end of all this, the air velocity is the whole velocity.
+        if let AirSpeed::InAir(velocity) = physics.air_speed {
+            lv.0 = velocity;
+            physics.fall_with_gravity(values);
         }
         physics.overall_rotation = overall_character_rotation;
     }
@@ -540,67 +740,78 @@ pub fn push_out_of_ground(
         &Rotation,
         &mut Transform,
         &FloorInfo,
-        &GlobalTransform,
+        &mut Position,
         &PlatformingCharacterControl,
         &PlatformingCharacterValues,
     )>,
-    mut gizmos: Gizmos,
-    spatial_query: SpatialQuery,
+    _gizmos: Gizmos,
+    // `SpatialQuery` reads every `Position`, which would conflict with moving the character.
+    spatial_query: Res<SpatialQueryPipeline>,
 ) {
-    for (
-        mut physics,
-        rb,
-        mut lv,
-        rot,
-        mut transform,
-        floor_info,
-        global_transform,
-        control,
-        values,
-    ) in query.iter_mut()
+    for (physics, _rb, _lv, _rot, _transform, _floor_info, mut position, _control, values) in
+        query.iter_mut()
     {
-        if let AirSpeed::Grounded { angle, slope_quat } = physics.air_speed {
+        if let AirSpeed::Grounded { slope_quat, .. } = physics.air_speed {
             let ground_cast_direction = slope_quat.mul_vec3(physics.ground_cast_direction);
-            let desired_distance_from_ground = values.cushion_radius;
+            let (desired_distance_from_ground, _) = values.body_radii(physics.rolling);
             let ground_cast = spatial_query.cast_ray(
-                global_transform.translation(),
+                position.0,
                 ground_cast_direction,
-                values.cushion_radius + values.ground_detection_radius, /* add a little overshoot */
+                desired_distance_from_ground + values.ground_detection_radius, /* add a little overshoot */
                 true,
                 SpatialQueryFilter::new().with_masks([MyCollisionLayers::Environment]),
             );
 
             // Check if we're on the ground or not.
-            match ground_cast {
-                Some(ground) => {
-                    // Check if we're floating above the ground a little bit.
-                    // If so, pull the character into the ground so they stick to it
-                    if ground.time_of_impact > desired_distance_from_ground {
-                        let dist_away_from_ground =
-                            (ground.time_of_impact - desired_distance_from_ground);
-                        if dist_away_from_ground > 0.0001 {
-                            info!("pull down by {:?}", dist_away_from_ground);
-                            transform.translation = transform.translation
-                                - (ground.normal.normalize() * dist_away_from_ground);
-                        }
+            if let Some(ground) = ground_cast {
+                // Check if we're floating above the ground a little bit.
+                // If so, pull the character into the ground so they stick to it
+                if ground.time_of_impact > desired_distance_from_ground {
+                    let dist_away_from_ground =
+                        ground.time_of_impact - desired_distance_from_ground;
+                    if dist_away_from_ground > 0.0001 {
+                        info!("pull down by {:?}", dist_away_from_ground);
+                        position.0 -= ground.normal.normalize() * dist_away_from_ground;
                     }
-                    // Check if we're stuck inside of the ground, and if so, push us out of it.
-                    else if ground.time_of_impact < desired_distance_from_ground {
-                        let dist_inside_ground =
-                            desired_distance_from_ground - ground.time_of_impact;
-                        if dist_inside_ground > 0.001 {
-                            transform.translation = transform.translation
-                                + (ground.normal.normalize() * dist_inside_ground);
-                            info!("push out of ground {:?}", dist_inside_ground);
-                        }
+                }
+                // Check if we're stuck inside of the ground, and if so, push us out of it.
+                else if ground.time_of_impact < desired_distance_from_ground {
+                    let dist_inside_ground = desired_distance_from_ground - ground.time_of_impact;
+                    if dist_inside_ground > 0.001 {
+                        position.0 += ground.normal.normalize() * dist_inside_ground;
+                        info!("push out of ground {:?}", dist_inside_ground);
                     }
                 }
-                None => {}
             }
         }
     }
 }
 
+/// Remembers where each character ended up this fixed tick.
+pub fn store_interpolation_translations(
+    mut query: Query<(&mut PlatformingCharacterInterpolation, &Position)>,
+) {
+    for (mut interpolation, position) in query.iter_mut() {
+        interpolation.previous_translation = interpolation.current_translation;
+        interpolation.current_translation = position.0;
+    }
+}
+
+/// Offsets each character's model so it's drawn between the last two fixed ticks.
+pub fn interpolate_character_models(
+    characters: Query<(&PlatformingCharacterInterpolation, &Transform), Without<Animated>>,
+    mut models: Query<(&mut Transform, &Parent), With<Animated>>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    let overstep = fixed_time.overstep_percentage();
+    for (mut model_transform, parent) in models.iter_mut() {
+        if let Ok((interpolation, character_transform)) = characters.get(parent.get()) {
+            model_transform.translation =
+                interpolation.translation(overstep) - character_transform.translation;
+        }
+    }
+}
+
 pub fn handle_collisions(
     collisions: Res<Collisions>,
     mut bodies: Query<(
@@ -610,8 +821,8 @@ pub fn handle_collisions(
         Option<&mut PlatformingCharacterPhysics>,
         Without<AsyncSceneCollider>,
     )>,
-    mut scene_bodies: Query<(&RigidBody, &Children, &Handle<Scene>)>,
-    mut gizmos: Gizmos,
+    _scene_bodies: Query<(&RigidBody, &Children, &Handle<Scene>)>,
+    _gizmos: Gizmos,
 ) {
     // Iterate through collisions and move the kinematic body to resolve penetration
     for contacts in collisions.iter() {
@@ -672,7 +883,7 @@ pub fn update_floor(
     sensors: Query<(&GlobalTransform, &ShapeCaster)>,
     mut gizmos: Gizmos,
 ) {
-    for (mut sensor_array, mut transform, global_transform, ..) in sensor_arrays.iter_mut() {
+    for (sensor_array, mut transform, global_transform, ..) in sensor_arrays.iter_mut() {
         let (control, platforming_physics, mut floor_info, ..) =
             characters.get_mut(sensor_array.character).unwrap();
         // determine slope
@@ -681,7 +892,7 @@ pub fn update_floor(
             sensor_array.collisions[CharacterSensor::FloorBack as usize],
         ) {
             (Some(front), Some(back)) => {
-                let direction = Vec3 {
+                let _direction = Vec3 {
                     x: control.facing_2d.x,
                     y: 0.0,
                     z: control.facing_2d.y,
@@ -693,10 +904,10 @@ pub fn update_floor(
                     sensors.get(sensor_array.sensors[CharacterSensor::FloorBack as usize]),
                 ) {
                     (
-                        Ok((front_target, ..)),
-                        Ok((back_target, ..)),
-                        Ok((front_sensor, front_sensor_caster, ..)),
-                        Ok((back_sensor, back_sensor_caster, ..)),
+                        Ok((_front_target, ..)),
+                        Ok((_back_target, ..)),
+                        Ok((_front_sensor, front_sensor_caster, ..)),
+                        Ok((_back_sensor, back_sensor_caster, ..)),
                     ) => {
                         // let front_point = global_transform.transform_point(front.point1);
                         // let back_point = global_transform.transform_point(back.point1);
@@ -706,7 +917,7 @@ pub fn update_floor(
                         let back_point = back.point1;
                         let front_normal = front.normal1;
                         let back_normal = back.normal1;
-                        let direction_angle = control.facing_2d.angle_between(Vec2::Y);
+                        let _direction_angle = control.facing_2d.angle_between(Vec2::Y);
                         let floor_sensor_back_to_front = Vec3::normalize(front_point - back_point);
                         let floor_normals = Vec3::normalize(front_normal + back_normal);
                         let up = floor_normals.reject_from_normalized(floor_sensor_back_to_front);
@@ -788,7 +999,7 @@ pub fn update_floor(
     }
 }
 
-fn ray_arrow_gizmo(mut gizmos: &mut Gizmos<'_>, start: Vec3, vector: Vec3, color: Color) {
+fn ray_arrow_gizmo(gizmos: &mut Gizmos<'_>, start: Vec3, vector: Vec3, color: Color) {
     let end = start + vector;
     gizmos.line(start, end, color);
 
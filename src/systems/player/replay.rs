@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+
+use crate::components::{
+    camera::OrbitCameraTarget,
+    input::PlayerSlot,
+    player::{
+        physics::PlatformingCharacterControl,
+        replay::{InputRecorder, InputRecording, InputReplay, RecordedInput, RecordedTick},
+    },
+};
+
+/// Where the recording hotkey saves to.
+pub const RECORDING_PATH: &str = "recording.walkyrec";
+
+/// Which player a character belongs to in a recording.
+fn recorded_player(slot: Option<&PlayerSlot>) -> u32 {
+    slot.map_or(0, |slot| slot.join_order)
+}
+
+/// Feeds the next recorded tick into each player's character, in place of the device input
+/// systems. Characters whose player isn't in the tick stand still.
+pub fn replay_inputs(
+    mut commands: Commands,
+    mut replay: ResMut<InputReplay>,
+    mut characters: Query<(
+        &mut PlatformingCharacterControl,
+        Option<&mut OrbitCameraTarget>,
+        Option<&PlayerSlot>,
+    )>,
+) {
+    let Some(tick) = replay.recording.ticks.get(replay.next_tick) else {
+        info!("replay finished after {} ticks", replay.next_tick);
+        for (mut control, _, _) in characters.iter_mut() {
+            control.move_input = Vec2::ZERO;
+            control.jump_pressed = false;
+            control.jump_press_latched = false;
+            control.crouch_pressed = false;
+        }
+        commands.remove_resource::<InputReplay>();
+        return;
+    };
+
+    for (mut control, camera_target, slot) in characters.iter_mut() {
+        let player = recorded_player(slot);
+        let input = tick
+            .players
+            .iter()
+            .find(|input| input.player == player)
+            .copied()
+            .unwrap_or_default();
+        control.move_input = input.move_input;
+        control.jump_pressed = input.jump_pressed;
+        control.jump_press_latched = input.jump_press_latched;
+        control.crouch_pressed = input.crouch_pressed;
+        if let Some(mut camera_target) = camera_target {
+            camera_target.yaw = input.camera_yaw;
+            camera_target.pitch = input.camera_pitch;
+        }
+    }
+    replay.next_tick += 1;
+}
+
+/// Appends what every character is about to act on this tick to the recording.
+pub fn record_inputs(
+    mut recorder: ResMut<InputRecorder>,
+    characters: Query<(
+        &PlatformingCharacterControl,
+        Option<&OrbitCameraTarget>,
+        Option<&PlayerSlot>,
+    )>,
+) {
+    let players = characters
+        .iter()
+        .map(|(control, camera_target, slot)| RecordedInput {
+            player: recorded_player(slot),
+            move_input: control.move_input,
+            jump_pressed: control.jump_pressed,
+            jump_press_latched: control.jump_press_latched,
+            crouch_pressed: control.crouch_pressed,
+            camera_yaw: camera_target.map_or(0.0, |c| c.yaw),
+            camera_pitch: camera_target.map_or(0.0, |c| c.pitch),
+        })
+        .collect();
+    recorder.recording.ticks.push(RecordedTick { players });
+}
+
+/// F9 saves everything recorded since startup to [`RECORDING_PATH`]. Recording keeps going, so
+/// pressing it again later saves a longer run. Nothing is recorded unless an `InputRecorder` was
+/// inserted at startup, which the demo does when it's run with `--record`.
+pub fn recording_hotkey(keys: Res<Input<KeyCode>>, recorder: Option<Res<InputRecorder>>) {
+    if !keys.just_pressed(KeyCode::F9) {
+        return;
+    }
+    match recorder {
+        Some(recorder) => save_recording(&recorder.recording),
+        None => warn!("not recording inputs, run with --record to record them"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_recording(recording: &InputRecording) {
+    match recording.save(RECORDING_PATH) {
+        Ok(()) => info!(
+            "saved {} ticks of input to {}",
+            recording.ticks.len(),
+            RECORDING_PATH
+        ),
+        Err(e) => warn!("failed to save recording to {}: {:?}", RECORDING_PATH, e),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_recording(recording: &InputRecording) {
+    warn!(
+        "can't save recordings on the web, dropping {} ticks",
+        recording.ticks.len()
+    );
+}
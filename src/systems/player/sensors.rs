@@ -55,7 +55,7 @@ pub fn update_sensors(
         &mut CharacterSensorArray,
         Without<PlatformingCharacterControl>,
     )>,
-    mut casters: Query<(
+    casters: Query<(
         &ShapeCaster,
         &CharacterSensorCaster,
         &ShapeHits,
@@ -64,23 +64,22 @@ pub fn update_sensors(
     mut gizmos: Gizmos,
 ) {
     for (mut sensor_array, _) in sensors.iter_mut() {
-        let (sensor_owner, control, transform, _) =
+        let (_sensor_owner, _control, _transform, _) =
             characters.get_mut(sensor_array.character).unwrap();
         for sensor in CharacterSensor::iter() {
             let sensor_index = sensor as usize;
             let caster_id = sensor_array.sensors[sensor_index];
             match casters.get(caster_id) {
-                Ok((caster, _, hits, gt)) => {
+                Ok((_caster, _, hits, gt)) => {
                     if hits.is_empty() {
                         gizmos.sphere(gt.translation(), Quat::default(), 0.5, Color::GREEN);
                         sensor_array.collisions[sensor_index] = None;
                         continue;
                     }
-                    for hit in hits.iter() {
+                    if let Some(hit) = hits.iter().next() {
                         gizmos.sphere(gt.translation(), Quat::default(), 0.5, Color::ORANGE);
                         gizmos.line(hit.point1, hit.point2, Color::ORANGE);
-                        sensor_array.collisions[sensor_index] = Some(hit.clone());
-                        break;
+                        sensor_array.collisions[sensor_index] = Some(*hit);
                     }
                 }
                 Err(e) => {
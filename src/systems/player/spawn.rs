@@ -1,101 +1,186 @@
+use bevy::input::gamepad::GamepadConnectionEvent;
 use bevy::prelude::*;
 
-use bevy_xpbd_3d::math::*;
 use bevy_xpbd_3d::prelude::*;
-use strum::EnumCount;
-use strum::IntoEnumIterator;
 
+use crate::components::input::{ActionState, PlayerSlot};
 use crate::components::player::animation::Animated;
+use crate::components::player::homing::HomingAttack;
+use crate::components::player::physics::ChargeDash;
 use crate::components::player::physics::FloorInfo;
 use crate::components::player::physics::PlatformingCharacterAnimationFlags;
-use crate::components::player::sensors::CharacterSensor;
-use crate::components::player::sensors::CharacterSensorArray;
+use crate::components::player::physics::PlatformingCharacterInterpolation;
 use crate::components::player::sensors::MyCollisionLayers;
 use crate::components::{
-    camera::{OrbitCameraTarget, ViewpointMappable, ViewpointMappedInput},
+    camera::{
+        CameraSpeedProfile, OrbitCameraTarget, SpeedCurve, ViewpointMappable, ViewpointMappedInput,
+    },
     player::physics::{
         PlatformingCharacterControl, PlatformingCharacterPhysics, PlatformingCharacterPhysicsAccel,
         PlatformingCharacterValues,
     },
 };
 
-use super::sensors::sensor_bundle;
+/// The components that make up a platforming character, without any visuals.
+pub fn platforming_character_bundle(transform: Transform) -> impl Bundle {
+    (
+        (
+            PlatformingCharacterPhysics {
+                ground_speed: Vec2::ZERO,
+                ground_direction: Vec2::X,
+                ground_cast_direction: Vec3::NEG_Y,
+                air_speed: crate::components::player::physics::AirSpeed::InAir(Vec3::ZERO),
+                wall_collision_normal: None,
+                overall_rotation: Quat::default(),
+                show_gizmos: false,
+                surface_rotation: Quat::IDENTITY,
+                slope_direction: Vec3::X,
+                rolling: false,
+                jumping: false,
+                coyote_time_left: 0.0,
+                charge_dash: ChargeDash::Idle,
+            },
+            SpatialBundle::from_transform(transform),
+            RigidBody::Kinematic,
+            Collider::ball(0.35),
+            PlatformingCharacterPhysicsAccel {
+                ground_acceleration: Vec2::ZERO,
+                ground_friction: 0.0,
+                jump: false,
+                jump_buffer_left: 0.0,
+                jump_pressed_last_tick: false,
+                crouch_pressed_last_tick: false,
+            },
+            PlatformingCharacterControl {
+                move_input: Vec2::ZERO,
+                facing_2d: Vec2::X,
+                jump_pressed: false,
+                jump_press_latched: false,
+                crouch_pressed: false,
+            },
+            PlatformingCharacterValues {
+                acceleration_speed: 32.0,
+                air_acceleration_speed: 16.0,
+                deceleration_speed: 44.8,
+                top_speed: 15.0,
+                friction_speed: 19.2,
+                gravity: Vec3::new(0.0, -12.8, 0.0),
+                air_drag: 0.1,
+                air_top_speed: 24.0,
+                jump_speed: 10.0,
+                jump_release_speed: 4.0,
+                coyote_time: 0.1,
+                jump_buffer_time: 0.1,
+                slope_factor: 20.0,
+                roll_uphill_slope_factor: 12.0,
+                roll_downhill_slope_factor: 50.0,
+                min_stick_speed: 6.0,
+                surface_adhesion: 6.4,
+                roll_start_speed: 2.5,
+                roll_stop_speed: 1.25,
+                roll_friction_speed: 9.6,
+                roll_deceleration_speed: 11.2,
+                rolling_radius: 0.25,
+                homing_range: 8.0,
+                homing_cone_angle: 1.0,
+                homing_speed: 24.0,
+                homing_max_dash_time: 1.0,
+                homing_bounce_speed: 8.0,
+                charge_dash_rev: 2.0,
+                charge_dash_max_charge: 8.0,
+                charge_dash_decay: 1.9,
+                charge_dash_base_speed: 20.0,
+                charge_dash_speed_per_charge: 1.25,
+                cushion_radius: 0.5,
+                ground_detection_radius: 0.2,
+                obstacle_detection_radius: 0.35,
+                slope_cast_distance: 2.0,
+            },
+            PlatformingCharacterAnimationFlags { skidding: false },
+            HomingAttack::default(),
+            PlatformingCharacterInterpolation::new(transform.translation),
+            FloorInfo {
+                up: Vec3::default(),
+                floor_sensor_origin_slope: Vec3::default(),
+                floor_sensor_cast_slope: Vec3::default(),
+                slope_pivot: Vec3::default(),
+            },
+            CollisionLayers::new(
+                [MyCollisionLayers::Player],
+                [MyCollisionLayers::Enemy, MyCollisionLayers::Environment],
+            ),
+        ),
+        (
+            OrbitCameraTarget {
+                distance: 5.0,
+                active: true,
+                yaw: 0.0,
+                pitch: 0.0,
+                probe_radius: 0.2,
+                min_distance: 1.0,
+                recovery_speed: 4.0,
+                auto_follow: true,
+                auto_follow_delay: 1.0,
+                auto_follow_rate: 2.0,
+                auto_follow_min_speed: 4.0,
+                auto_follow_pitch_factor: 0.5,
+                time_since_look: 0.0,
+                speed_profile: CameraSpeedProfile {
+                    min_speed: 6.0,
+                    max_speed: 24.0,
+                    fov: SpeedCurve {
+                        at_min_speed: std::f32::consts::FRAC_PI_4,
+                        at_max_speed: 1.2,
+                        exponent: 1.5,
+                    },
+                    distance_scale: SpeedCurve {
+                        at_min_speed: 1.0,
+                        at_max_speed: 1.3,
+                        exponent: 1.0,
+                    },
+                    smoothing: 3.0,
+                },
+            },
+            ViewpointMappable {
+                forward: Quat::default(),
+            },
+            ViewpointMappedInput {
+                move_input: Vec2::ZERO,
+                control_lock: None,
+            },
+            ActionState::default(),
+        ),
+    )
+}
 
 /// set up a simple 3D scene
 pub fn spawn_player(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    _meshes: ResMut<Assets<Mesh>>,
+    _materials: ResMut<Assets<StandardMaterial>>,
     asset_server: Res<AssetServer>,
 ) {
-    let mut player = commands.spawn((
-        PlatformingCharacterPhysics {
-            ground_speed: Vec2::ZERO,
-            ground_direction: Vec2::X,
-            ground_cast_direction: Vec3::NEG_Y,
-            air_speed: crate::components::player::physics::AirSpeed::InAir(0.0),
-            wall_running: false,
-            wall_collision_normal: None,
-            overall_rotation: Quat::default(),
-            show_gizmos: false,
-            ceiling_run_quat: None
-        },
-        SpatialBundle::from_transform(Transform::from_xyz(0.0, 2.0, 0.0)),
-        RigidBody::Kinematic,
-        Collider::ball(0.35),
-    ));
-    player
-        .insert(PlatformingCharacterPhysicsAccel {
-            ground_acceleration: Vec2::ZERO,
-            ground_friction: 0.0,
-            air_acceleration: 0.0,
-        })
-        .insert(PlatformingCharacterControl {
-            move_input: Vec2::ZERO,
-            facing_2d: Vec2::X,
-            jump_pressed: false,
-        })
-        .insert(PlatformingCharacterValues {
-            acceleration_speed: 0.50,
-            air_acceleration_speed: 0.25,
-            deceleration_speed: 0.70,
-            top_speed: 15.0,
-            friction_speed: 0.30,
-            gravity: -0.2,
-            jump_speed: 2.0,
-            cushion_radius: 0.5,
-            ground_detection_radius: 0.2,
-            obstacle_detection_radius: 0.35,
-            slope_cast_distance: 2.0,
-        })
-        .insert(PlatformingCharacterAnimationFlags { skidding: false })
-        .insert(OrbitCameraTarget {
-            distance: 5.0,
-            active: true,
-            yaw: 0.0,
-            pitch: 0.0,
-        })
-        .insert(ViewpointMappable {
-            forward: Quat::default(),
-        })
-        .insert(ViewpointMappedInput {
-            move_input: Vec2::ZERO,
-        })
-        .insert(FloorInfo {
-            up: Vec3::default(),
-            floor_sensor_origin_slope: Vec3::default(),
-            floor_sensor_cast_slope: Vec3::default(),
-            slope_pivot: Vec3::default(),
-        })
-        .insert(CollisionLayers::new(
-            [MyCollisionLayers::Player],
-            [MyCollisionLayers::Enemy, MyCollisionLayers::Environment],
-        ));
+    spawn_character(
+        &mut commands,
+        &asset_server,
+        Transform::from_xyz(0.0, 2.0, 0.0),
+        PlayerSlot::keyboard_mouse(),
+    );
+}
+
+/// Spawns a character with its model, driven by the devices in `slot`.
+pub fn spawn_character(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    transform: Transform,
+    slot: PlayerSlot,
+) -> Entity {
+    let player = commands.spawn((platforming_character_bundle(transform), slot));
 
     let player_id = player.id();
-    info!("Player is entity {:?}", player_id);
+    info!("Player is entity {:?}, driven by {:?}", player_id, slot);
 
-    let model = commands
+    let _model = commands
         .spawn((
             SceneBundle {
                 scene: asset_server.load("degauss.glb#Scene0"),
@@ -103,7 +188,9 @@ pub fn spawn_player(
                 ..default()
             },
             Animated {
-                current_animation: 0,
+                // Starts in the state machine's initial state once it's loaded.
+                state: String::new(),
+                time_in_state: 0.0,
                 speed: 1.0,
             },
         ))
@@ -137,4 +224,72 @@ pub fn spawn_player(
     //     ))
     //     .push_children(&sc)
     //     .set_parent(player_id);
+
+    player_id
+}
+
+/// Gives a gamepad to the first player when it presses a button, if they don't have one yet, and
+/// otherwise spawns a new player for it.
+pub fn join_players(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut players: Query<&mut PlayerSlot>,
+) {
+    let mut joined: Vec<Gamepad> = Vec::new();
+    let mut next_join_order = players
+        .iter()
+        .map(|slot| slot.join_order + 1)
+        .max()
+        .unwrap_or(0);
+    for button in gamepad_buttons.get_just_pressed() {
+        let gamepad = button.gamepad;
+        if joined.contains(&gamepad) || players.iter().any(|slot| slot.gamepad == Some(gamepad)) {
+            continue;
+        }
+        joined.push(gamepad);
+
+        if let Some(mut slot) = players.iter_mut().find(|slot| slot.gamepad.is_none()) {
+            info!("{:?} joined an existing player", gamepad);
+            slot.gamepad = Some(gamepad);
+            continue;
+        }
+
+        // Spread new players out so they don't spawn inside each other.
+        let offset = (players.iter().count() + joined.len()) as f32 * 2.0;
+        spawn_character(
+            &mut commands,
+            &asset_server,
+            Transform::from_xyz(offset, 2.0, 0.0),
+            PlayerSlot {
+                join_order: next_join_order,
+                ..PlayerSlot::gamepad(gamepad)
+            },
+        );
+        next_join_order += 1;
+    }
+}
+
+/// Takes disconnected gamepads away from their players. Players left without any device are
+/// despawned.
+pub fn leave_players(
+    mut commands: Commands,
+    mut connections: EventReader<GamepadConnectionEvent>,
+    mut players: Query<(Entity, &mut PlayerSlot)>,
+) {
+    for event in connections.read() {
+        if !event.disconnected() {
+            continue;
+        }
+        for (player, mut slot) in players.iter_mut() {
+            if slot.gamepad != Some(event.gamepad) {
+                continue;
+            }
+            slot.gamepad = None;
+            if !slot.keyboard_mouse {
+                info!("{:?} disconnected, despawning {:?}", event.gamepad, player);
+                commands.entity(player).despawn_recursive();
+            }
+        }
+    }
 }
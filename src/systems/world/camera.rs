@@ -1,29 +1,41 @@
-use bevy::{math::vec3, prelude::*};
-use smooth_bevy_cameras::{
-    controllers::{
-        orbit::{OrbitCameraBundle, OrbitCameraController},
-        unreal::{UnrealCameraBundle, UnrealCameraController},
-    },
-    LookTransform, LookTransformBundle, Smoother,
-};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use bevy::{prelude::*, render::camera::Viewport, window::PrimaryWindow};
+use bevy_easings::EaseFunction;
+use bevy_xpbd_3d::prelude::*;
+use smooth_bevy_cameras::controllers::unreal::{UnrealCameraBundle, UnrealCameraController};
 
 use crate::components::{
-    camera::{MyCameraMarker, OrbitCameraTarget, ViewpointMappable, ViewpointMappedInput},
-    player::physics::PlatformingCharacterControl,
+    camera::{
+        CameraTransition, CameraZone, CameraZoneMode, ControlLock, OrbitCameraTarget, PlayerCamera,
+        SplitScreen, ViewpointMappable, ViewpointMappedInput,
+    },
+    input::{ActionState, PlayerSlot},
+    player::{
+        physics::{
+            AirSpeed, PlatformingCharacterControl, PlatformingCharacterInterpolation,
+            PlatformingCharacterPhysics, PlatformingCharacterValues,
+        },
+        sensors::MyCollisionLayers,
+    },
 };
 
-pub fn setup_camera(mut commands: Commands) {
-    let light = commands
-        .spawn(PointLightBundle {
-            point_light: PointLight {
-                intensity: 1500.0,
-                shadows_enabled: true,
-                ..default()
-            },
-            transform: Transform::from_xyz(0.0, 3.0, 0.0),
+/// The light that travels with each camera.
+fn camera_light() -> PointLightBundle {
+    PointLightBundle {
+        point_light: PointLight {
+            intensity: 1500.0,
+            shadows_enabled: true,
             ..default()
-        })
-        .id();
+        },
+        transform: Transform::from_xyz(0.0, 3.0, 0.0),
+        ..default()
+    }
+}
+
+/// Spawns the free camera, which is used while no player has a camera of their own.
+pub fn setup_camera(mut commands: Commands) {
+    let light = commands.spawn(camera_light()).id();
     commands
         .spawn(UnrealCameraBundle::new(
             smooth_bevy_cameras::controllers::unreal::UnrealCameraController::default(),
@@ -35,75 +47,579 @@ pub fn setup_camera(mut commands: Commands) {
         .insert(Camera3dBundle::default());
 }
 
+/// Gives every active `OrbitCameraTarget` its own camera, and despawns cameras whose target has
+/// gone or been deactivated.
+pub fn spawn_player_cameras(
+    mut commands: Commands,
+    targets: Query<(Entity, &OrbitCameraTarget)>,
+    cameras: Query<(Entity, &PlayerCamera)>,
+) {
+    for (camera, player_camera) in cameras.iter() {
+        let target_active = targets
+            .get(player_camera.target)
+            .is_ok_and(|(_, target)| target.active);
+        if !target_active {
+            commands.entity(camera).despawn_recursive();
+        }
+    }
+
+    for (entity, target) in targets.iter() {
+        if target.active && !cameras.iter().any(|(_, camera)| camera.target == entity) {
+            let light = commands.spawn(camera_light()).id();
+            commands
+                .spawn((
+                    Camera3dBundle::default(),
+                    PlayerCamera {
+                        target: entity,
+                        distance: target.distance,
+                        speed: 0.0,
+                        zone: None,
+                        transition: None,
+                    },
+                ))
+                .add_child(light);
+        }
+    }
+}
+
+/// A part of the window, in physical pixels from the top left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ViewportRect {
+    pub position: UVec2,
+    pub size: UVec2,
+}
+
+impl ViewportRect {
+    fn from_corners(min: UVec2, max: UVec2) -> Self {
+        Self {
+            position: min,
+            size: max - min,
+        }
+    }
+
+    fn halves(self, split: SplitScreen) -> [ViewportRect; 2] {
+        let min = self.position;
+        let max = self.position + self.size;
+        let mid = self.position + self.size / 2;
+        match split {
+            SplitScreen::Horizontal => [
+                Self::from_corners(min, UVec2::new(max.x, mid.y)),
+                Self::from_corners(UVec2::new(min.x, mid.y), max),
+            ],
+            SplitScreen::Vertical => [
+                Self::from_corners(min, UVec2::new(mid.x, max.y)),
+                Self::from_corners(UVec2::new(mid.x, min.y), max),
+            ],
+        }
+    }
+}
+
+/// Divides a window between `players` cameras. One player gets the whole window and two split it
+/// along `split`. With three, the second half is split the other way, and four or more get a
+/// grid.
+pub fn split_screen_layout(
+    players: usize,
+    window_size: UVec2,
+    split: SplitScreen,
+) -> Vec<ViewportRect> {
+    let window = ViewportRect {
+        position: UVec2::ZERO,
+        size: window_size,
+    };
+    let other_split = match split {
+        SplitScreen::Horizontal => SplitScreen::Vertical,
+        SplitScreen::Vertical => SplitScreen::Horizontal,
+    };
+
+    match players {
+        0 => Vec::new(),
+        1 => vec![window],
+        2 => window.halves(split).to_vec(),
+        3 => {
+            let [first, rest] = window.halves(split);
+            let [second, third] = rest.halves(other_split);
+            vec![first, second, third]
+        }
+        _ => {
+            let columns = (players as f32).sqrt().ceil() as u32;
+            let rows = (players as u32).div_ceil(columns);
+            let corner = |column: u32, row: u32| {
+                UVec2::new(window_size.x * column / columns, window_size.y * row / rows)
+            };
+            (0..players as u32)
+                .map(|i| {
+                    let (column, row) = (i % columns, i / columns);
+                    ViewportRect::from_corners(corner(column, row), corner(column + 1, row + 1))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Lays the player cameras out across the window, in the order their players joined by
+/// `PlayerSlot::join_order`, and switches to the free camera while there are none. Characters
+/// without a slot go last.
+pub fn layout_split_screen(
+    split: Res<SplitScreen>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut player_cameras: Query<(&PlayerCamera, &mut Camera), Without<UnrealCameraController>>,
+    mut free_cameras: Query<(&mut Camera, &mut UnrealCameraController)>,
+    slots: Query<&PlayerSlot>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let window_size = UVec2::new(window.physical_width(), window.physical_height());
+    if window_size.x == 0 || window_size.y == 0 {
+        // Minimized, and zero sized viewports aren't allowed.
+        return;
+    }
+
+    let mut cameras: Vec<_> = player_cameras.iter_mut().collect();
+    // Players keep their place on screen in the order they joined.
+    cameras.sort_by_key(|(player_camera, _)| {
+        let join_order = slots
+            .get(player_camera.target)
+            .map_or(u32::MAX, |slot| slot.join_order);
+        (join_order, player_camera.target)
+    });
+    let layout = split_screen_layout(cameras.len(), window_size, *split);
+
+    for (order, ((_, mut camera), rect)) in cameras.into_iter().zip(&layout).enumerate() {
+        let unchanged = camera.viewport.as_ref().is_some_and(|viewport| {
+            viewport.physical_position == rect.position && viewport.physical_size == rect.size
+        });
+        if !unchanged {
+            camera.viewport = Some(Viewport {
+                physical_position: rect.position,
+                physical_size: rect.size,
+                ..default()
+            });
+        }
+        // Cameras drawing to the same window need distinct orders.
+        if camera.order != order as isize {
+            camera.order = order as isize;
+        }
+    }
+
+    let free = layout.is_empty();
+    for (mut camera, mut controller) in free_cameras.iter_mut() {
+        if camera.is_active != free {
+            camera.is_active = free;
+        }
+        controller.enabled = free;
+    }
+}
+
+/// Works out which `CameraZone` each player camera's target is in, and starts a blend when that
+/// changes.
+pub fn update_camera_zones(
+    mut cameras: Query<(&mut PlayerCamera, &Transform)>,
+    targets: Query<&GlobalTransform, With<OrbitCameraTarget>>,
+    zones: Query<&CameraZone>,
+    spatial_query: SpatialQuery,
+) {
+    for (mut player_camera, camera_transform) in cameras.iter_mut() {
+        let Ok(target_transform) = targets.get(player_camera.target) else {
+            continue;
+        };
+        let zone = spatial_query
+            .shape_intersections(
+                &Collider::ball(0.1),
+                target_transform.translation(),
+                Quat::IDENTITY,
+                SpatialQueryFilter::new().with_masks([MyCollisionLayers::CameraZone]),
+            )
+            .into_iter()
+            .filter_map(|entity| Some((entity, zones.get(entity).ok()?)))
+            .max_by_key(|(_, zone)| zone.priority);
+        let zone_entity = zone.map(|(entity, _)| entity);
+        if zone_entity == player_camera.zone {
+            continue;
+        }
+
+        // Blend with the zone being entered, or if there isn't one, the zone being left.
+        let blend_zone = zone
+            .map(|(_, zone)| zone)
+            .or_else(|| player_camera.zone.and_then(|left| zones.get(left).ok()));
+        player_camera.transition = blend_zone.map(|zone| CameraTransition {
+            from: *camera_transform,
+            elapsed: 0.0,
+            duration: zone.blend_time,
+            easing: zone.easing,
+        });
+        player_camera.zone = zone_entity;
+    }
+}
+
+/// Where a zone puts the camera, given where the target is drawn. `None` if the zone can't place
+/// it, like a rail with no points.
+fn zone_camera_transform(mode: &CameraZoneMode, target_translation: Vec3) -> Option<Transform> {
+    match mode {
+        CameraZoneMode::FixedAngle {
+            yaw,
+            pitch,
+            distance,
+        } => Some(
+            Transform::from_translation(
+                target_translation + orbit_direction(*yaw, *pitch) * *distance,
+            )
+            .looking_at(target_translation, Vec3::Y),
+        ),
+        CameraZoneMode::LookAt {
+            point,
+            distance,
+            height,
+        } => {
+            let away = (target_translation - *point)
+                .try_normalize()
+                .unwrap_or(Vec3::X);
+            let position = target_translation + away * *distance + Vec3::Y * *height;
+            Some(Transform::from_translation(position).looking_at(*point, Vec3::Y))
+        }
+        CameraZoneMode::Rail(rail) => rail.closest_point(target_translation).map(|position| {
+            Transform::from_translation(position).looking_at(target_translation, Vec3::Y)
+        }),
+    }
+}
+
+/// Evaluates `function` at `t`, which is clamped to 0 to 1. bevy_easings only runs its curves on
+/// its own easing components, so these are the same formulas it uses, for blending by hand. The
+/// sine and elastic curves turn a quarter as far per unit as its versions do, so they start at 0
+/// and end at 1 like the rest.
+pub fn ease(function: EaseFunction, t: f32) -> f32 {
+    let p = t.clamp(0.0, 1.0);
+    let bounce_out = |p: f32| {
+        if p < 4.0 / 11.0 {
+            (121.0 * p * p) / 16.0
+        } else if p < 8.0 / 11.0 {
+            (363.0 / 40.0 * p * p) - (99.0 / 10.0 * p) + 17.0 / 5.0
+        } else if p < 9.0 / 10.0 {
+            (4356.0 / 361.0 * p * p) - (35442.0 / 1805.0 * p) + 16061.0 / 1805.0
+        } else {
+            (54.0 / 5.0 * p * p) - (513.0 / 25.0 * p) + 268.0 / 25.0
+        }
+    };
+    let back = |f: f32| f * f * f - f * (f * PI).sin();
+    match function {
+        EaseFunction::QuadraticIn => p * p,
+        EaseFunction::QuadraticOut => -(p * (p - 2.0)),
+        EaseFunction::QuadraticInOut if p < 0.5 => 2.0 * p * p,
+        EaseFunction::QuadraticInOut => (-2.0 * p * p) + (4.0 * p) - 1.0,
+
+        EaseFunction::CubicIn => p * p * p,
+        EaseFunction::CubicOut => (p - 1.0).powi(3) + 1.0,
+        EaseFunction::CubicInOut if p < 0.5 => 4.0 * p * p * p,
+        EaseFunction::CubicInOut => 0.5 * (2.0 * p - 2.0).powi(3) + 1.0,
+
+        EaseFunction::QuarticIn => p.powi(4),
+        EaseFunction::QuarticOut => (p - 1.0).powi(3) * (1.0 - p) + 1.0,
+        EaseFunction::QuarticInOut if p < 0.5 => 8.0 * p.powi(4),
+        EaseFunction::QuarticInOut => -8.0 * (p - 1.0).powi(4) + 1.0,
+
+        EaseFunction::QuinticIn => p.powi(5),
+        EaseFunction::QuinticOut => (p - 1.0).powi(5) + 1.0,
+        EaseFunction::QuinticInOut if p < 0.5 => 16.0 * p.powi(5),
+        EaseFunction::QuinticInOut => 0.5 * (2.0 * p - 2.0).powi(5) + 1.0,
+
+        EaseFunction::SineIn => ((p - 1.0) * FRAC_PI_2).sin() + 1.0,
+        EaseFunction::SineOut => (p * FRAC_PI_2).sin(),
+        EaseFunction::SineInOut => 0.5 * (1.0 - (p * PI).cos()),
+
+        EaseFunction::CircularIn => 1.0 - (1.0 - p * p).sqrt(),
+        EaseFunction::CircularOut => ((2.0 - p) * p).sqrt(),
+        EaseFunction::CircularInOut if p < 0.5 => 0.5 * (1.0 - (1.0 - 4.0 * p * p).sqrt()),
+        EaseFunction::CircularInOut => 0.5 * ((-(2.0 * p - 3.0) * (2.0 * p - 1.0)).sqrt() + 1.0),
+
+        EaseFunction::ExponentialIn if p == 0.0 => 0.0,
+        EaseFunction::ExponentialIn => 2f32.powf(10.0 * (p - 1.0)),
+        EaseFunction::ExponentialOut if p == 1.0 => 1.0,
+        EaseFunction::ExponentialOut => 1.0 - 2f32.powf(-10.0 * p),
+        EaseFunction::ExponentialInOut if p == 0.0 || p == 1.0 => p,
+        EaseFunction::ExponentialInOut if p < 0.5 => 0.5 * 2f32.powf(20.0 * p - 10.0),
+        EaseFunction::ExponentialInOut => -0.5 * 2f32.powf(-20.0 * p + 10.0) + 1.0,
+
+        EaseFunction::ElasticIn => (13.0 * FRAC_PI_2 * p).sin() * 2f32.powf(10.0 * (p - 1.0)),
+        EaseFunction::ElasticOut => {
+            (-13.0 * FRAC_PI_2 * (p + 1.0)).sin() * 2f32.powf(-10.0 * p) + 1.0
+        }
+        EaseFunction::ElasticInOut if p < 0.5 => {
+            0.5 * (13.0 * FRAC_PI_2 * (2.0 * p)).sin() * 2f32.powf(10.0 * (2.0 * p - 1.0))
+        }
+        EaseFunction::ElasticInOut => {
+            0.5 * ((-13.0 * FRAC_PI_2 * (2.0 * p)).sin() * 2f32.powf(-10.0 * (2.0 * p - 1.0)) + 2.0)
+        }
+
+        EaseFunction::BackIn => back(p),
+        EaseFunction::BackOut => 1.0 - back(1.0 - p),
+        EaseFunction::BackInOut if p < 0.5 => 0.5 * back(2.0 * p),
+        EaseFunction::BackInOut => 0.5 * (1.0 - back(2.0 - 2.0 * p)) + 0.5,
+
+        EaseFunction::BounceIn => 1.0 - bounce_out(1.0 - p),
+        EaseFunction::BounceOut => bounce_out(p),
+        EaseFunction::BounceInOut if p < 0.5 => 0.5 * (1.0 - bounce_out(1.0 - 2.0 * p)),
+        EaseFunction::BounceInOut => 0.5 * bounce_out(2.0 * p - 1.0) + 0.5,
+    }
+}
+
+/// Which way from the target an orbiting camera sits.
+fn orbit_direction(yaw: f32, pitch: f32) -> Vec3 {
+    let xz_len = f32::cos(pitch);
+    Vec3::new(
+        xz_len * f32::cos(yaw),
+        f32::sin(pitch),
+        xz_len * f32::sin(-yaw),
+    )
+}
+
+/// Moves each player camera into orbit around its target. Walls between the target and the camera
+/// pull it in, and it eases back out once they're out of the way. The faster the target goes, the
+/// wider the field of view and the further back the camera sits.
 pub fn update_camera(
-    mut cameras: Query<(
-        &mut UnrealCameraController,
-        &mut LookTransform,
-        &mut Transform,
+    mut cameras: Query<
+        (&mut PlayerCamera, &mut Transform, &mut Projection),
         Without<OrbitCameraTarget>,
-    )>,
-    mut targets: Query<(
+    >,
+    targets: Query<(
         &OrbitCameraTarget,
         &Transform,
-        &mut ViewpointMappable,
-        Without<LookTransform>,
+        Option<&PlatformingCharacterInterpolation>,
+        Option<&PlatformingCharacterPhysics>,
     )>,
+    zones: Query<&CameraZone>,
+    fixed_time: Res<Time<Fixed>>,
+    time: Res<Time>,
+    spatial_query: SpatialQuery,
     mut gizmos: Gizmos,
 ) {
-    let mut last_target: Option<&OrbitCameraTarget> = None;
-    for (mut unreal_camera, mut look_transform, mut camera_transform, _) in cameras.iter_mut() {
-        for (target, target_transform, mut viewpoint_mappable, _) in targets.iter_mut() {
-            // Get camera target yaw and pitch, and compute vector
-            let xz_len = f32::cos(target.pitch);
-            let direction = Vec3::new(
-                xz_len * f32::cos(target.yaw),
-                f32::sin(target.pitch),
-                xz_len * f32::sin(target.yaw * -1.0),
-            );
-            // Multiply it by the desired distance and add it to the target's position.
-            let camera_target_position =
-                (direction * target.distance) + target_transform.translation;
-
-            if target.active {
-                unreal_camera.enabled = false;
-                look_transform.target = target_transform.translation;
-                // Move the camera there.
-                camera_transform.translation = camera_target_position;
-                camera_transform.look_at(target_transform.translation, Vec3::Y);
-
-                viewpoint_mappable.forward = camera_transform.rotation;
-                return;
+    let orbit = |target: &OrbitCameraTarget,
+                 target_transform: &Transform,
+                 interpolation: Option<&PlatformingCharacterInterpolation>| {
+        // Characters move at the fixed rate, so follow where they're drawn instead.
+        let target_translation = match interpolation {
+            Some(interpolation) => interpolation.translation(fixed_time.overstep_percentage()),
+            None => target_transform.translation,
+        };
+        let direction = orbit_direction(target.yaw, target.pitch);
+        (target_translation, direction * target.distance)
+    };
+
+    for (mut player_camera, mut camera_transform, mut projection) in cameras.iter_mut() {
+        let Ok((target, target_transform, interpolation, physics)) =
+            targets.get(player_camera.target)
+        else {
+            continue;
+        };
+        let (target_translation, offset) = orbit(target, target_transform, interpolation);
+
+        let profile = &target.speed_profile;
+        let speed = physics.map_or(0.0, PlatformingCharacterPhysics::speed);
+        let blend = 1.0 - f32::exp(-profile.smoothing * time.delta_seconds());
+        player_camera.speed += (speed - player_camera.speed) * blend;
+        let t = profile.speed_fraction(player_camera.speed);
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = profile.fov.sample(t);
+        }
+        let distance = target.distance * profile.distance_scale.sample(t);
+
+        // Find how far out the camera can go before it hits a wall.
+        let min_distance = target.min_distance.min(distance);
+        let clear_distance = spatial_query
+            .cast_shape(
+                &Collider::ball(target.probe_radius),
+                target_translation,
+                Quat::default(),
+                offset.normalize_or_zero(),
+                distance,
+                true,
+                SpatialQueryFilter::new().with_masks([MyCollisionLayers::Environment]),
+            )
+            .map_or(distance, |hit| hit.time_of_impact)
+            .max(min_distance);
+        // Snap in straight away so walls never block the view, but ease back out.
+        let recovered = player_camera.distance + target.recovery_speed * time.delta_seconds();
+        player_camera.distance = recovered.min(clear_distance);
+
+        let orbit_transform = Transform::from_translation(
+            target_translation + offset.normalize_or_zero() * player_camera.distance,
+        )
+        .looking_at(target_translation, Vec3::Y);
+
+        // Zones are authored, so they're trusted to keep the camera out of walls.
+        let desired = player_camera
+            .zone
+            .and_then(|zone| zones.get(zone).ok())
+            .and_then(|zone| zone_camera_transform(&zone.mode, target_translation))
+            .unwrap_or(orbit_transform);
+
+        // Move the camera there, blending from where it was if it just changed zones.
+        let mut new_transform = desired;
+        if let Some(transition) = player_camera.transition.as_mut() {
+            transition.elapsed += time.delta_seconds();
+            let t = (transition.elapsed / transition.duration.max(f32::EPSILON)).min(1.0);
+            let eased = ease(transition.easing, t);
+            new_transform.translation =
+                transition.from.translation.lerp(desired.translation, eased);
+            new_transform.rotation = transition.from.rotation.slerp(desired.rotation, eased);
+            if t >= 1.0 {
+                player_camera.transition = None;
             }
+        }
+        camera_transform.translation = new_transform.translation;
+        camera_transform.rotation = new_transform.rotation;
+    }
 
-            // It's not active, let's draw a gizmo so we can examine it.
-            gizmos.ray(
-                target_transform.translation,
-                direction * target.distance,
-                Color::BLUE,
-            );
-            gizmos.sphere(camera_target_position, Quat::default(), 0.5, Color::BLUE);
+    for (target, target_transform, interpolation, _) in targets.iter() {
+        if target.active {
+            continue;
         }
+        // It's not active, let's draw a gizmo so we can examine it.
+        let (target_translation, offset) = orbit(target, target_transform, interpolation);
+        gizmos.ray(target_translation, offset, Color::BLUE);
+        gizmos.sphere(
+            target_translation + offset,
+            Quat::default(),
+            0.5,
+            Color::BLUE,
+        );
+    }
+}
+
+/// Which way, and how fast, a character is moving across the plane square to `up`, which the
+/// camera turns in. `planar_velocity` is in the frame of `surface_rotation`, and the result is
+/// in the frame of the air rotation, so it's world X and Z under normal gravity. Running straight
+/// up a wall has no heading.
+pub fn follow_heading(planar_velocity: Vec2, surface_rotation: Quat, up: Vec3) -> Vec2 {
+    let velocity = surface_rotation * Vec3::new(planar_velocity.x, 0.0, planar_velocity.y);
+    let flattened = velocity - up * velocity.dot(up);
+    (Quat::from_rotation_arc(Vec3::Y, up).inverse() * flattened).xz()
+}
+
+/// Eases each camera around behind the way its character is moving, once the player has left the
+/// camera alone for a while, and tilts it with the slope the character is running on.
+pub fn auto_follow_camera(
+    mut targets: Query<(
+        &mut OrbitCameraTarget,
+        &ActionState,
+        &PlatformingCharacterPhysics,
+        &PlatformingCharacterValues,
+    )>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (mut target, actions, physics, values) in targets.iter_mut() {
+        if actions.look != Vec2::ZERO || actions.camera_reset.just_pressed {
+            target.time_since_look = 0.0;
+            continue;
+        }
+        target.time_since_look += dt;
+
+        let heading = follow_heading(
+            physics.planar_velocity(),
+            physics.surface_rotation,
+            values.up(),
+        );
+        if !target.auto_follow
+            || target.time_since_look < target.auto_follow_delay
+            || heading.length() < target.auto_follow_min_speed
+        {
+            continue;
+        }
+        let blend = 1.0 - f32::exp(-target.auto_follow_rate * dt);
+
+        // Behind the direction of motion, the same way the camera reset faces.
+        let behind_yaw = f32::atan2(heading.y, -heading.x);
+        let yaw_error = (behind_yaw - target.yaw + PI).rem_euclid(TAU) - PI;
+        target.yaw += yaw_error * blend;
 
-        unreal_camera.enabled = true;
+        if let AirSpeed::Grounded { .. } = physics.air_speed {
+            let incline = physics
+                .slope_direction
+                .dot(values.up())
+                .clamp(-1.0, 1.0)
+                .asin();
+            let slope_pitch = -incline * target.auto_follow_pitch_factor;
+            target.pitch += (slope_pitch - target.pitch) * blend;
+        }
+    }
+}
+
+/// How far the surface has to turn while the stick is held before the control lock holds
+/// forward steady, in radians.
+pub const CONTROL_LOCK_ANGLE: f32 = 0.35;
+
+/// How the stick maps onto a surface seen from a camera, in the surface's 2D frame, which is the
+/// frame ground speed is in. The first column is stick right, and the second is stick up.
+pub fn stick_basis(camera_rotation: Quat, surface_rotation: Quat) -> Mat2 {
+    let surface_up = surface_rotation * Vec3::Y;
+    let onto_surface = |v: Vec3| v - surface_up * v.dot(surface_up);
+    let right = onto_surface(camera_rotation * Vec3::X);
+    let mut forward = onto_surface(camera_rotation * Vec3::NEG_Z);
+    if forward.length_squared() < 1e-4 {
+        // Looking straight at the surface, so up on the screen is forward.
+        forward = onto_surface(camera_rotation * Vec3::Y);
+    }
+    let to_2d = |v: Vec3| {
+        let local = surface_rotation.inverse() * v;
+        Vec2::new(local.x, local.z).normalize_or_zero()
+    };
+    Mat2::from_cols(to_2d(right), to_2d(forward))
+}
+
+/// Maps `stick` into the surface's 2D frame. `lock` is taken when the stick leaves neutral and
+/// dropped when it goes back. Once the surface has turned more than [`CONTROL_LOCK_ANGLE`] away
+/// from where it was then, the camera's last mapping from before that is used instead of its
+/// current one.
+pub fn map_stick(
+    lock: &mut Option<ControlLock>,
+    stick: Vec2,
+    camera_rotation: Quat,
+    surface_rotation: Quat,
+) -> Vec2 {
+    if stick == Vec2::ZERO {
+        *lock = None;
+        return Vec2::ZERO;
+    }
+
+    let surface_up = surface_rotation * Vec3::Y;
+    let from_camera = stick_basis(camera_rotation, surface_rotation);
+    let lock = lock.get_or_insert(ControlLock {
+        surface_up,
+        stick_basis: from_camera,
+    });
+    // Follow the camera until the surface turns, so the mapping that's held is the one from just
+    // before it did.
+    if lock.surface_up.angle_between(surface_up) <= CONTROL_LOCK_ANGLE {
+        lock.stick_basis = from_camera;
     }
+    (lock.stick_basis * stick).normalize_or_zero() * stick.length()
 }
 
+/// Maps each character's stick onto the surface it's on, relative to the camera following it, or
+/// its `ViewpointMappable` if none is. While the stick stays held through a change in surface,
+/// the mapping from before the change is kept.
 pub fn project_input_camera(
     mut targets: Query<(
+        Entity,
         &mut ViewpointMappedInput,
         &ViewpointMappable,
         &mut PlatformingCharacterControl,
+        &PlatformingCharacterPhysics,
         &Transform,
     )>,
-    mut gizmos: Gizmos,
+    cameras: Query<(&PlayerCamera, &Transform), Without<PlatformingCharacterControl>>,
+    _gizmos: Gizmos,
 ) {
-    for (mut input_to_map, orientation, mut control, transform) in targets.iter_mut() {
-        let input = Vec3::new(
-            input_to_map.move_input.x,
-            0.0,
-            input_to_map.move_input.y * -1.0,
-        );
-        let forward = orientation.forward.mul_vec3(input);
+    for (entity, mut input_to_map, orientation, mut control, physics, _transform) in
+        targets.iter_mut()
+    {
+        let stick = std::mem::take(&mut input_to_map.move_input);
+        let camera_rotation = cameras
+            .iter()
+            .find(|(camera, _)| camera.target == entity)
+            .map_or(orientation.forward, |(_, camera_transform)| {
+                camera_transform.rotation
+            });
 
         // gizmos.ray(
         //     transform.translation,
@@ -121,9 +637,11 @@ pub fn project_input_camera(
         //     Color::BLUE,
         // );
 
-        let result = forward.xz().normalize_or_zero() * input.length();
-        control.move_input = result;
-
-        input_to_map.move_input = Vec2::ZERO;
+        control.move_input = map_stick(
+            &mut input_to_map.control_lock,
+            stick,
+            camera_rotation,
+            physics.surface_rotation,
+        );
     }
 }
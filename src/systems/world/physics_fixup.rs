@@ -19,9 +19,9 @@ pub fn fixup_nested_colliders(
         &Handle<Scene>,
     )>,
 ) {
-    for (mut nc, e, collider_transform, _, _) in nested_colliders.iter_mut() {
+    for (nc, e, collider_transform, _, _) in nested_colliders.iter_mut() {
         match scene_bodies.get(nc.get()) {
-            Ok((parent, collision_layers, parent_transform, collider_parent_entity, _, _)) => {
+            Ok((parent, collision_layers, _parent_transform, _collider_parent_entity, _, _)) => {
                 /*info!(
                     "Cloning parent rigidbody ({:?} from {:?}) onto {:?}",
                     parent, collider_parent_entity, e
@@ -31,11 +31,11 @@ pub fn fixup_nested_colliders(
                     collider_transform, parent_transform
                 );
                 */
-                let rb = parent.clone();
+                let rb = *parent;
                 commands.entity(e).insert(rb);
-                commands.entity(e).insert(collision_layers.clone());
+                commands.entity(e).insert(*collision_layers);
                 commands.entity(e).insert(ReapplyColliderTransform {
-                    desired: collider_transform.clone(),
+                    desired: *collider_transform,
                     lgtm_remaining: 5,
                 });
             }
@@ -61,10 +61,10 @@ pub fn reapply_collider_transform(
                 "Reapplying collider transform {:?} over {:?} for entity {:?}",
                 reapply, current, entity
             ); */
-            commands.entity(entity).insert(reapply.desired.clone());
+            commands.entity(entity).insert(reapply.desired);
         } else {
             reapply.lgtm_remaining -= 1;
-            if (reapply.lgtm_remaining <= 0) {
+            if reapply.lgtm_remaining == 0 {
                 commands.entity(entity).remove::<ReapplyColliderTransform>(); // you can rest now
             }
         }
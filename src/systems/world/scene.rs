@@ -1,17 +1,11 @@
 use bevy::prelude::*;
+use bevy_easings::EaseFunction;
 
-use bevy_xpbd_3d::math::*;
-use bevy_xpbd_3d::parry::transformation::voxelization::FillMode;
 use bevy_xpbd_3d::prelude::*;
 
+use crate::components::camera::{CameraZone, CameraZoneMode};
+use crate::components::player::homing::HomingTargetable;
 use crate::components::player::sensors::MyCollisionLayers;
-use crate::components::{
-    camera::{OrbitCameraTarget, ViewpointMappable, ViewpointMappedInput},
-    player::physics::{
-        PlatformingCharacterControl, PlatformingCharacterPhysics, PlatformingCharacterPhysicsAccel,
-        PlatformingCharacterValues,
-    },
-};
 
 /// set up a simple 3D scene
 pub fn setup_scene(
@@ -43,6 +37,42 @@ pub fn setup_scene(
         ),
         RigidBody::Dynamic,
     ));
+    // homing attack targets, floating in a row
+    for i in 0..3 {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::UVSphere {
+                    radius: 0.4,
+                    ..default()
+                })),
+                material: materials.add(Color::rgb_u8(255, 120, 90).into()),
+                transform: Transform::from_xyz(-4.0 - 4.0 * i as f32, 3.0 + i as f32, 4.0),
+                ..default()
+            },
+            Collider::ball(0.4),
+            CollisionLayers::new([MyCollisionLayers::Enemy], [MyCollisionLayers::Player]),
+            RigidBody::Static,
+            HomingTargetable,
+        ));
+    }
+    // side view of the homing targets, from a camera zone around them
+    commands.spawn((
+        SpatialBundle::from_transform(Transform::from_xyz(-8.0, 3.0, 4.0)),
+        Collider::cuboid(12.0, 6.0, 4.0),
+        Sensor,
+        CollisionLayers::new([MyCollisionLayers::CameraZone], [MyCollisionLayers::Player]),
+        RigidBody::Static,
+        CameraZone {
+            mode: CameraZoneMode::FixedAngle {
+                yaw: -std::f32::consts::FRAC_PI_2,
+                pitch: 0.2,
+                distance: 10.0,
+            },
+            priority: 0,
+            blend_time: 0.75,
+            easing: EaseFunction::QuadraticInOut,
+        },
+    ));
     // light
     commands.spawn(PointLightBundle {
         point_light: PointLight {
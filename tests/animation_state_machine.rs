@@ -0,0 +1,118 @@
+//! The character's animation state machine is plain data, so its transitions are checked without
+//! loading the model.
+use bevy_walky::components::player::animation::{AnimationContext, AnimationStateMachine};
+
+fn machine() -> AnimationStateMachine {
+    let ron = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/degauss.anim.ron"
+    ))
+    .unwrap();
+    AnimationStateMachine::from_ron(&ron).unwrap()
+}
+
+fn grounded(speed: f32) -> AnimationContext {
+    AnimationContext {
+        grounded: true,
+        speed,
+        top_speed: 15.0,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn every_transition_leads_to_a_state() {
+    let machine = machine();
+    assert!(machine.state(&machine.initial_state).is_some());
+    let transitions = machine
+        .any_state
+        .iter()
+        .chain(machine.states.iter().flat_map(|state| &state.transitions));
+    for transition in transitions {
+        assert!(
+            machine.state(&transition.to).is_some(),
+            "no state named {:?}",
+            transition.to
+        );
+    }
+}
+
+#[test]
+fn running_speeds_up_through_walk_to_run() {
+    let machine = machine();
+    assert_eq!(machine.next_state("idle", &grounded(0.0)), None);
+    assert_eq!(machine.next_state("idle", &grounded(5.0)), Some("walk"));
+    assert_eq!(machine.next_state("walk", &grounded(12.0)), Some("run"));
+    // Between the thresholds, running stays running.
+    assert_eq!(machine.next_state("run", &grounded(9.5)), None);
+    assert_eq!(machine.next_state("run", &grounded(5.0)), Some("walk"));
+}
+
+#[test]
+fn skidding_interrupts_running() {
+    let machine = machine();
+    let skidding = AnimationContext {
+        skidding: true,
+        ..grounded(12.0)
+    };
+    assert_eq!(machine.next_state("run", &skidding), Some("skid"));
+    assert_eq!(machine.next_state("skid", &grounded(12.0)), Some("walk"));
+}
+
+#[test]
+fn jumps_rise_fall_and_land() {
+    let machine = machine();
+    let rising = AnimationContext {
+        grounded: false,
+        vertical_speed: 8.0,
+        ..Default::default()
+    };
+    let falling = AnimationContext {
+        vertical_speed: -3.0,
+        ..rising
+    };
+    assert_eq!(machine.next_state("walk", &rising), Some("jump_rise"));
+    assert_eq!(machine.next_state("jump_rise", &falling), Some("fall"));
+    assert_eq!(machine.next_state("fall", &grounded(0.0)), Some("land"));
+
+    let landed = AnimationContext {
+        time_in_state: 0.2,
+        ..grounded(0.0)
+    };
+    assert_eq!(machine.next_state("land", &grounded(0.0)), None);
+    assert_eq!(machine.next_state("land", &landed), Some("idle"));
+}
+
+#[test]
+fn rolling_and_charging_win_from_any_state() {
+    let machine = machine();
+    let rolling = AnimationContext {
+        rolling: true,
+        ..grounded(8.0)
+    };
+    assert_eq!(machine.next_state("walk", &rolling), Some("roll"));
+    assert_eq!(machine.next_state("roll", &rolling), None);
+    assert_eq!(machine.next_state("roll", &grounded(8.0)), Some("walk"));
+
+    let charging = AnimationContext {
+        dash_charge: Some(0.5),
+        ..grounded(0.0)
+    };
+    assert_eq!(machine.next_state("idle", &charging), Some("charge"));
+    assert_eq!(machine.next_state("charge", &rolling), Some("roll"));
+}
+
+#[test]
+fn clip_speed_follows_the_character() {
+    let machine = machine();
+    let walk = machine.state("walk").unwrap();
+    assert_eq!(walk.speed.evaluate(&grounded(15.0)), 3.0);
+    assert_eq!(walk.speed.evaluate(&grounded(0.0)), 0.5);
+
+    let charge = machine.state("charge").unwrap();
+    let charging = AnimationContext {
+        dash_charge: Some(0.5),
+        ..grounded(0.0)
+    };
+    assert_eq!(charge.speed.evaluate(&charging), 2.5);
+}
@@ -0,0 +1,76 @@
+//! Camera transitions blend with bevy_easings' curves, evaluated by hand.
+use bevy_easings::EaseFunction::{self, *};
+use bevy_walky::systems::world::camera::ease;
+
+const ALL: [EaseFunction; 30] = [
+    QuadraticIn,
+    QuadraticOut,
+    QuadraticInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuarticIn,
+    QuarticOut,
+    QuarticInOut,
+    QuinticIn,
+    QuinticOut,
+    QuinticInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    CircularIn,
+    CircularOut,
+    CircularInOut,
+    ExponentialIn,
+    ExponentialOut,
+    ExponentialInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+];
+
+#[test]
+fn every_curve_starts_at_zero_and_ends_at_one() {
+    for function in ALL {
+        assert!(
+            ease(function, 0.0).abs() < 1e-5,
+            "{function:?} starts off zero"
+        );
+        assert!(
+            (ease(function, 1.0) - 1.0).abs() < 1e-5,
+            "{function:?} ends off one"
+        );
+    }
+}
+
+#[test]
+fn time_outside_the_transition_is_clamped() {
+    for function in ALL {
+        assert_eq!(ease(function, -0.5), ease(function, 0.0), "{function:?}");
+        assert_eq!(ease(function, 1.5), ease(function, 1.0), "{function:?}");
+    }
+}
+
+#[test]
+fn in_out_curves_are_halfway_at_the_middle() {
+    for function in [
+        QuadraticInOut,
+        CubicInOut,
+        QuarticInOut,
+        QuinticInOut,
+        SineInOut,
+        CircularInOut,
+        ExponentialInOut,
+        ElasticInOut,
+        BackInOut,
+        BounceInOut,
+    ] {
+        assert!((ease(function, 0.5) - 0.5).abs() < 1e-5, "{function:?}");
+    }
+}
@@ -0,0 +1,93 @@
+//! The camera swings around behind the way the character is heading once the player stops
+//! turning it.
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_6, PI, TAU};
+
+use bevy::prelude::*;
+use bevy_walky::{
+    components::camera::OrbitCameraTarget,
+    harness::{CharacterHarness, ScriptedInput},
+    systems::world::camera::follow_heading,
+};
+use bevy_xpbd_3d::prelude::*;
+
+fn assert_near(actual: Vec2, expected: Vec2) {
+    assert!(
+        (actual - expected).length() < 1e-4,
+        "expected {expected}, got {actual}"
+    );
+}
+
+/// How far `yaw` is from `expected`, the short way around.
+fn yaw_error(yaw: f32, expected: f32) -> f32 {
+    ((yaw - expected + PI).rem_euclid(TAU) - PI).abs()
+}
+
+/// Runs along +X for four seconds, long enough for the camera to settle, and returns its yaw.
+fn settled_yaw(harness: &mut CharacterHarness) -> f32 {
+    harness.run(4 * 64, |_| ScriptedInput::moving(Vec2::X));
+    harness
+        .app()
+        .world
+        .get::<OrbitCameraTarget>(harness.character())
+        .unwrap()
+        .yaw
+}
+
+#[test]
+fn heading_on_flat_ground_is_the_ground_speed() {
+    let heading = follow_heading(Vec2::new(3.0, -4.0), Quat::IDENTITY, Vec3::Y);
+    assert_near(heading, Vec2::new(3.0, -4.0));
+}
+
+#[test]
+fn heading_on_a_ceiling_is_turned_around() {
+    // Upside down after going around a loop along +X, so the surface's +X is the world's -X.
+    let ceiling = Quat::from_rotation_z(PI);
+    let heading = follow_heading(Vec2::new(5.0, 0.0), ceiling, Vec3::Y);
+    assert_near(heading, Vec2::new(-5.0, 0.0));
+}
+
+#[test]
+fn running_up_a_wall_has_no_heading() {
+    let wall = Quat::from_rotation_z(FRAC_PI_2);
+    assert_near(
+        follow_heading(Vec2::new(5.0, 0.0), wall, Vec3::Y),
+        Vec2::ZERO,
+    );
+    // Running along it still heads that way.
+    assert_near(
+        follow_heading(Vec2::new(0.0, 5.0), wall, Vec3::Y),
+        Vec2::new(0.0, 5.0),
+    );
+}
+
+#[test]
+fn heading_follows_gravity() {
+    // Gravity pulls along +X, so the surface's +X, square to it, is the world's +Y.
+    let up = Vec3::NEG_X;
+    let air_rotation = Quat::from_rotation_arc(Vec3::Y, up);
+    let heading = follow_heading(Vec2::new(5.0, 0.0), air_rotation, up);
+    assert_near(heading, Vec2::new(5.0, 0.0));
+}
+
+#[test]
+fn camera_settles_behind_on_flat_ground() {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(Collider::cuboid(200.0, 0.1, 200.0), Transform::default());
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+    let yaw = settled_yaw(&mut harness);
+    // Behind a character running along +X is towards -X.
+    assert!(yaw_error(yaw, PI) < 0.02, "settled at {yaw}");
+}
+
+#[test]
+fn camera_settles_behind_up_a_slope() {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(
+        Collider::cuboid(200.0, 0.1, 200.0),
+        Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_6)),
+    );
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+    let yaw = settled_yaw(&mut harness);
+    assert!(yaw_error(yaw, PI) < 0.02, "settled at {yaw}");
+}
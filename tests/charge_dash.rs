@@ -0,0 +1,134 @@
+//! The charge dash revs up while crouching in place and launches the character as a roll.
+use bevy::prelude::*;
+use bevy_walky::{
+    components::player::physics::{
+        ChargeDash, PlatformingCharacterPhysics, PlatformingCharacterValues,
+    },
+    harness::{CharacterHarness, ScriptedInput, HARNESS_TICK_HZ},
+};
+use bevy_xpbd_3d::prelude::*;
+
+/// A character standing still on flat ground.
+fn standing() -> CharacterHarness {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(Collider::cuboid(100.0, 0.1, 100.0), Transform::default());
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+    harness.run(10, |_| ScriptedInput::default());
+    harness
+}
+
+fn physics(harness: &CharacterHarness) -> &PlatformingCharacterPhysics {
+    harness
+        .app()
+        .world
+        .get::<PlatformingCharacterPhysics>(harness.character())
+        .unwrap()
+}
+
+fn values(harness: &CharacterHarness) -> &PlatformingCharacterValues {
+    harness
+        .app()
+        .world
+        .get::<PlatformingCharacterValues>(harness.character())
+        .unwrap()
+}
+
+fn charge(harness: &CharacterHarness) -> Option<f32> {
+    match physics(harness).charge_dash {
+        ChargeDash::Idle => None,
+        ChargeDash::Charging { charge } => Some(charge),
+    }
+}
+
+/// Crouches, then taps jump once, aiming along `aim`.
+fn start_charging(harness: &mut CharacterHarness, aim: Vec2) {
+    harness.tick(ScriptedInput::moving(aim).with_crouch(true));
+    harness.tick(ScriptedInput::moving(aim).with_crouch(true).with_jump(true));
+    harness.tick(ScriptedInput::moving(aim).with_crouch(true));
+}
+
+#[test]
+fn charging_starts_standing_still_on_the_ground() {
+    let mut harness = standing();
+    start_charging(&mut harness, Vec2::ZERO);
+    assert!(
+        charge(&harness).is_some(),
+        "{:?}",
+        physics(&harness).charge_dash
+    );
+}
+
+#[test]
+fn charging_doesnt_start_in_the_air() {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(Collider::cuboid(100.0, 0.1, 100.0), Transform::default());
+    harness.spawn_character(Transform::from_xyz(0.0, 5.0, 0.0));
+    start_charging(&mut harness, Vec2::ZERO);
+    assert_eq!(physics(&harness).charge_dash, ChargeDash::Idle);
+}
+
+#[test]
+fn charging_doesnt_start_while_running() {
+    let mut harness = standing();
+    harness.run(30, |_| ScriptedInput::moving(Vec2::X));
+    start_charging(&mut harness, Vec2::X);
+    assert_eq!(physics(&harness).charge_dash, ChargeDash::Idle);
+}
+
+#[test]
+fn charge_drains_away_and_is_capped() {
+    let mut harness = standing();
+    start_charging(&mut harness, Vec2::ZERO);
+    let max_charge = values(&harness).charge_dash_max_charge;
+    let decay = values(&harness).charge_dash_decay;
+
+    // Mashing jump tops the charge out.
+    for _ in 0..20 {
+        harness.tick(ScriptedInput::default().with_crouch(true).with_jump(true));
+        harness.tick(ScriptedInput::default().with_crouch(true));
+    }
+    let mashed = charge(&harness).unwrap();
+    assert!(mashed <= max_charge, "charged past the cap to {mashed}");
+    assert!(mashed > max_charge * 0.9, "only charged to {mashed}");
+
+    // Holding crouch without pressing jump lets it drain for half a second.
+    harness.run(32, |_| ScriptedInput::default().with_crouch(true));
+    let drained = charge(&harness).unwrap();
+    let expected = mashed * (-decay * 0.5).exp();
+    assert!(
+        (drained - expected).abs() < 1e-3,
+        "drained to {drained}, expected {expected}"
+    );
+}
+
+#[test]
+fn releasing_launches_a_roll() {
+    let mut harness = standing();
+    start_charging(&mut harness, Vec2::Y);
+    for _ in 0..3 {
+        harness.tick(
+            ScriptedInput::moving(Vec2::Y)
+                .with_crouch(true)
+                .with_jump(true),
+        );
+        harness.tick(ScriptedInput::moving(Vec2::Y).with_crouch(true));
+    }
+    let charged = charge(&harness).unwrap();
+    let values = values(&harness);
+    let launch_speed =
+        values.charge_dash_base_speed + charged * values.charge_dash_speed_per_charge;
+    // The roll's friction takes a little off on the tick it launches.
+    let friction_per_tick = values.roll_friction_speed / HARNESS_TICK_HZ as f32;
+
+    harness.tick(ScriptedInput::default());
+    let physics = physics(&harness);
+    assert_eq!(physics.charge_dash, ChargeDash::Idle);
+    assert!(physics.rolling, "the dash didn't come out as a roll");
+    let speed = physics.ground_speed.length();
+    assert!(
+        speed <= launch_speed && speed >= launch_speed - friction_per_tick - 1e-3,
+        "launched at {speed}, expected {launch_speed}"
+    );
+    // Aimed with the input while charging.
+    assert!(physics.ground_speed.normalize().dot(Vec2::Y) > 0.99);
+}
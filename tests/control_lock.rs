@@ -0,0 +1,106 @@
+//! The stick mapping is plain math, so the control lock is checked without an app.
+use bevy::prelude::*;
+use bevy_walky::{
+    components::camera::ControlLock,
+    systems::world::camera::{map_stick, stick_basis},
+};
+
+/// A camera behind the character, looking along +X.
+fn camera() -> Quat {
+    Transform::default().looking_to(Vec3::X, Vec3::Y).rotation
+}
+
+/// The surface `degrees` of the way around a loop that runs along +X.
+fn around_loop(degrees: f32) -> Quat {
+    Quat::from_rotation_z(degrees.to_radians())
+}
+
+fn assert_near(actual: Vec2, expected: Vec2) {
+    assert!(
+        (actual - expected).length() < 1e-4,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn stick_up_is_away_from_the_camera() {
+    let basis = stick_basis(camera(), Quat::IDENTITY);
+    assert_near(basis * Vec2::Y, Vec2::X);
+    assert_near(basis * Vec2::X, Vec2::Y);
+}
+
+#[test]
+fn forward_holds_around_a_loop() {
+    let mut lock: Option<ControlLock> = None;
+    // The surface turns a little each frame, like it does while running around a loop.
+    for step in 0..=36 {
+        let degrees = step as f32 * 5.0;
+        let move_input = map_stick(&mut lock, Vec2::Y, camera(), around_loop(degrees));
+        assert_near(move_input, Vec2::X);
+    }
+    assert!(lock.is_some());
+
+    // Upside down at the top, the camera alone would map stick up backwards.
+    let from_camera = stick_basis(camera(), around_loop(180.0)) * Vec2::Y;
+    assert_near(from_camera, Vec2::NEG_X);
+}
+
+#[test]
+fn forward_holds_through_a_sudden_quarter_turn() {
+    let mut lock = None;
+    assert_near(
+        map_stick(&mut lock, Vec2::Y, camera(), Quat::IDENTITY),
+        Vec2::X,
+    );
+    assert_near(
+        map_stick(&mut lock, Vec2::Y, camera(), around_loop(90.0)),
+        Vec2::X,
+    );
+    assert_near(
+        map_stick(&mut lock, Vec2::Y, camera(), around_loop(180.0)),
+        Vec2::X,
+    );
+}
+
+#[test]
+fn turning_the_camera_before_the_surface_turns_is_kept() {
+    let mut lock = None;
+    assert_near(
+        map_stick(&mut lock, Vec2::Y, camera(), Quat::IDENTITY),
+        Vec2::X,
+    );
+    // Still on flat ground, the player swings the camera around to look along +Z.
+    let turned = Transform::default().looking_to(Vec3::Z, Vec3::Y).rotation;
+    assert_near(
+        map_stick(&mut lock, Vec2::Y, turned, Quat::IDENTITY),
+        Vec2::Y,
+    );
+    // Then the surface turns, and forward stays where the camera last had it.
+    assert_near(
+        map_stick(&mut lock, Vec2::Y, turned, around_loop(90.0)),
+        Vec2::Y,
+    );
+    assert_near(
+        map_stick(&mut lock, Vec2::Y, turned, around_loop(180.0)),
+        Vec2::Y,
+    );
+}
+
+#[test]
+fn letting_go_releases_the_lock() {
+    let mut lock = None;
+    map_stick(&mut lock, Vec2::Y, camera(), Quat::IDENTITY);
+    map_stick(&mut lock, Vec2::Y, camera(), around_loop(180.0));
+
+    assert_eq!(
+        map_stick(&mut lock, Vec2::ZERO, camera(), around_loop(180.0)),
+        Vec2::ZERO
+    );
+    assert!(lock.is_none());
+
+    // Held again on the ceiling, the stick follows the camera from there.
+    assert_near(
+        map_stick(&mut lock, Vec2::Y, camera(), around_loop(180.0)),
+        Vec2::NEG_X,
+    );
+}
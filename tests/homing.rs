@@ -0,0 +1,148 @@
+//! In the air, the character locks on to the nearest target in front of it, and pressing jump
+//! dashes at it and bounces off.
+use bevy::{ecs::event::ManualEventReader, prelude::*};
+use bevy_walky::{
+    components::player::{
+        homing::{HomingAttack, HomingTargetEvent, HomingTargetable},
+        physics::{AirSpeed, PlatformingCharacterPhysics, PlatformingCharacterValues},
+        sensors::MyCollisionLayers,
+    },
+    harness::{CharacterHarness, ScriptedInput},
+};
+use bevy_xpbd_3d::prelude::*;
+
+/// A character falling through empty space, facing along +X.
+fn falling() -> CharacterHarness {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_character(Transform::from_xyz(0.0, 5.0, 0.0));
+    harness.run(2, |_| ScriptedInput::moving(Vec2::X));
+    harness
+}
+
+/// Spawns a target `offset` away from where the character is now.
+fn spawn_target(harness: &mut CharacterHarness, offset: Vec3) -> Entity {
+    let translation = harness.sample().position + offset;
+    harness
+        .app_mut()
+        .world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(translation)),
+            Collider::ball(0.4),
+            CollisionLayers::new([MyCollisionLayers::Enemy], [MyCollisionLayers::Player]),
+            RigidBody::Static,
+            HomingTargetable,
+        ))
+        .id()
+}
+
+fn target(harness: &CharacterHarness) -> Option<Entity> {
+    harness
+        .app()
+        .world
+        .get::<HomingAttack>(harness.character())
+        .unwrap()
+        .target
+}
+
+fn physics(harness: &CharacterHarness) -> &PlatformingCharacterPhysics {
+    harness
+        .app()
+        .world
+        .get::<PlatformingCharacterPhysics>(harness.character())
+        .unwrap()
+}
+
+#[test]
+fn acquires_a_target_in_front() {
+    let mut harness = falling();
+    let enemy = spawn_target(&mut harness, Vec3::new(4.0, 0.0, 0.0));
+    harness.run(2, |_| ScriptedInput::moving(Vec2::X));
+    assert_eq!(target(&harness), Some(enemy));
+}
+
+#[test]
+fn ignores_a_target_behind() {
+    let mut harness = falling();
+    spawn_target(&mut harness, Vec3::new(-4.0, 0.0, 0.0));
+    harness.run(2, |_| ScriptedInput::moving(Vec2::X));
+    assert_eq!(target(&harness), None);
+}
+
+#[test]
+fn ignores_a_target_outside_the_cone() {
+    let mut harness = falling();
+    spawn_target(&mut harness, Vec3::new(0.0, 0.0, 4.0));
+    harness.run(2, |_| ScriptedInput::moving(Vec2::X));
+    assert_eq!(target(&harness), None);
+}
+
+#[test]
+fn ignores_a_target_behind_a_wall() {
+    let mut harness = falling();
+    let wall = harness.sample().position + Vec3::new(2.0, 0.0, 0.0);
+    harness.spawn_static_collider(
+        Collider::cuboid(0.2, 4.0, 4.0),
+        Transform::from_translation(wall),
+    );
+    spawn_target(&mut harness, Vec3::new(4.0, 0.0, 0.0));
+    harness.run(2, |_| ScriptedInput::moving(Vec2::X));
+    assert_eq!(target(&harness), None);
+}
+
+#[test]
+fn cone_follows_gravity() {
+    let mut harness = CharacterHarness::new();
+    let character = harness.spawn_character(Transform::from_xyz(0.0, 5.0, 0.0));
+    // With gravity pulling along +X, the plane the character steers across turns a quarter turn,
+    // and facing +X across it faces up the world.
+    harness
+        .app_mut()
+        .world
+        .get_mut::<PlatformingCharacterValues>(character)
+        .unwrap()
+        .gravity = Vec3::new(12.8, 0.0, 0.0);
+    harness.run(2, |_| ScriptedInput::moving(Vec2::X));
+    let enemy = spawn_target(&mut harness, Vec3::new(0.0, 4.0, 0.0));
+    // Straight down, where +X points in the world.
+    spawn_target(&mut harness, Vec3::new(3.0, 0.0, 0.0));
+    harness.run(2, |_| ScriptedInput::moving(Vec2::X));
+    assert_eq!(target(&harness), Some(enemy));
+}
+
+#[test]
+fn dash_bounces_off_the_target() {
+    let mut harness = falling();
+    let enemy = spawn_target(&mut harness, Vec3::new(3.0, 0.0, 0.0));
+    harness.run(2, |_| ScriptedInput::moving(Vec2::X));
+    assert_eq!(target(&harness), Some(enemy));
+
+    let mut reader = ManualEventReader::<HomingTargetEvent>::default();
+    let mut hit = false;
+    harness.tick(ScriptedInput::moving(Vec2::X).with_jump(true));
+    for _ in 0..64 {
+        harness.tick(ScriptedInput::default());
+        let events = harness.app().world.resource::<Events<HomingTargetEvent>>();
+        if reader
+            .read(events)
+            .any(|event| matches!(event, HomingTargetEvent::Hit { target, .. } if *target == enemy))
+        {
+            hit = true;
+            break;
+        }
+    }
+    assert!(hit, "the dash never reached the target");
+
+    let bounce_speed = harness
+        .app()
+        .world
+        .get::<PlatformingCharacterValues>(harness.character())
+        .unwrap()
+        .homing_bounce_speed;
+    let AirSpeed::InAir(velocity) = physics(&harness).air_speed else {
+        panic!("landed on the target");
+    };
+    assert!(
+        velocity.abs_diff_eq(Vec3::Y * bounce_speed, 1e-3),
+        "bounced off at {velocity}"
+    );
+}
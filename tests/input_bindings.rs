@@ -0,0 +1,322 @@
+//! Bindings resolve into actions without an app, so they're mostly checked against synthetic
+//! input. How the controller reads them is checked in a harness app.
+use bevy::prelude::*;
+use bevy_walky::{
+    components::{
+        input::{Action, ActionState, Binding, InputBindings, InputSources},
+        player::physics::PlatformingCharacterPhysics,
+    },
+    harness::CharacterHarness,
+    systems::player::control::PendingRebind,
+};
+use bevy_xpbd_3d::prelude::*;
+
+struct Devices {
+    keyboard_mouse: bool,
+    keys: Input<KeyCode>,
+    mouse_buttons: Input<MouseButton>,
+    mouse_motion: Vec2,
+    mouse_look: bool,
+    gamepad_buttons: Input<GamepadButton>,
+    gamepad_axes: Axis<GamepadAxis>,
+    gamepads: Vec<Gamepad>,
+}
+
+impl Default for Devices {
+    fn default() -> Self {
+        Self {
+            keyboard_mouse: true,
+            keys: default(),
+            mouse_buttons: default(),
+            mouse_motion: Vec2::ZERO,
+            mouse_look: true,
+            gamepad_buttons: default(),
+            gamepad_axes: default(),
+            gamepads: Vec::new(),
+        }
+    }
+}
+
+impl Devices {
+    fn with_gamepad() -> Self {
+        Self {
+            gamepads: vec![Gamepad::new(0)],
+            ..default()
+        }
+    }
+
+    fn resolve(&self, bindings: &InputBindings) -> ActionState {
+        bindings.resolve(&self.sources())
+    }
+
+    fn sources(&self) -> InputSources<'_> {
+        InputSources {
+            keyboard_mouse: self.keyboard_mouse,
+            keys: &self.keys,
+            mouse_buttons: &self.mouse_buttons,
+            mouse_motion: self.mouse_motion,
+            mouse_look: self.mouse_look,
+            gamepad_buttons: &self.gamepad_buttons,
+            gamepad_axes: &self.gamepad_axes,
+            gamepads: &self.gamepads,
+        }
+    }
+}
+
+#[test]
+fn arrow_keys_move_at_full_length() {
+    let mut devices = Devices::default();
+    devices.keys.press(KeyCode::Up);
+    devices.keys.press(KeyCode::Right);
+
+    let actions = devices.resolve(&InputBindings::default());
+    assert!((actions.move_input - Vec2::new(1.0, 1.0).normalize()).length() < 1e-6);
+}
+
+#[test]
+fn jump_is_pressed_until_released() {
+    let bindings = InputBindings::default();
+    let mut devices = Devices::default();
+
+    devices.keys.press(KeyCode::Space);
+    let actions = devices.resolve(&bindings);
+    assert!(actions.jump.pressed && actions.jump.just_pressed);
+
+    devices.keys.clear();
+    let actions = devices.resolve(&bindings);
+    assert!(actions.jump.pressed && !actions.jump.just_pressed);
+
+    devices.keys.release(KeyCode::Space);
+    let actions = devices.resolve(&bindings);
+    assert!(!actions.jump.pressed && actions.jump.just_released);
+}
+
+#[test]
+fn one_of_two_bindings_released_keeps_the_action_held() {
+    let bindings = InputBindings::default();
+    let mut devices = Devices::with_gamepad();
+    let south = GamepadButton::new(Gamepad::new(0), GamepadButtonType::South);
+    devices.keys.press(KeyCode::Space);
+    devices.gamepad_buttons.press(south);
+    devices.keys.clear();
+    devices.gamepad_buttons.clear();
+
+    devices.keys.release(KeyCode::Space);
+    let actions = devices.resolve(&bindings);
+    assert!(actions.jump.pressed);
+    assert!(!actions.jump.just_released);
+}
+
+#[test]
+fn stick_inside_deadzone_is_ignored() {
+    let bindings = InputBindings::default();
+    let mut devices = Devices::with_gamepad();
+    let stick_x = GamepadAxis::new(Gamepad::new(0), GamepadAxisType::LeftStickX);
+
+    devices.gamepad_axes.set(stick_x, 0.2);
+    assert_eq!(devices.resolve(&bindings).move_input, Vec2::ZERO);
+
+    devices.gamepad_axes.set(stick_x, 0.5);
+    assert_eq!(devices.resolve(&bindings).move_input, Vec2::X);
+}
+
+#[test]
+fn unlisted_gamepads_are_ignored() {
+    let bindings = InputBindings::default();
+    let mut devices = Devices::with_gamepad();
+    let other = GamepadButton::new(Gamepad::new(1), GamepadButtonType::South);
+    devices.gamepad_buttons.press(other);
+
+    assert!(!devices.resolve(&bindings).jump.pressed);
+}
+
+#[test]
+fn gamepad_players_ignore_the_keyboard() {
+    let bindings = InputBindings::default();
+    let mut devices = Devices {
+        keyboard_mouse: false,
+        ..Devices::with_gamepad()
+    };
+    devices.keys.press(KeyCode::Space);
+    devices.keys.press(KeyCode::Up);
+
+    let actions = devices.resolve(&bindings);
+    assert!(!actions.jump.pressed);
+    assert_eq!(actions.move_input, Vec2::ZERO);
+    assert_eq!(Binding::just_pressed(&devices.sources()), None);
+}
+
+#[test]
+fn rebinding_replaces_only_the_same_device() {
+    let mut bindings = InputBindings::default();
+    assert!(bindings.rebind(Action::Jump, Binding::Key(KeyCode::Z)));
+    assert_eq!(
+        bindings.bindings(Action::Jump),
+        &[
+            Binding::GamepadButton(GamepadButtonType::South),
+            Binding::Key(KeyCode::Z),
+        ]
+    );
+
+    let mut devices = Devices::default();
+    devices.keys.press(KeyCode::Space);
+    assert!(!devices.resolve(&bindings).jump.pressed);
+    devices.keys.press(KeyCode::Z);
+    assert!(devices.resolve(&bindings).jump.pressed);
+}
+
+#[test]
+fn buttons_cant_replace_sticks() {
+    let mut bindings = InputBindings::default();
+    let before = bindings.clone();
+
+    assert!(!bindings.rebind(Action::Move, Binding::Key(KeyCode::W)));
+    assert!(!bindings.rebind(Action::Look, Binding::MouseButton(MouseButton::Right)));
+    assert!(!bindings.rebind(
+        Action::Jump,
+        Binding::GamepadStick {
+            x: GamepadAxisType::LeftStickX,
+            y: GamepadAxisType::LeftStickY,
+        }
+    ));
+    assert_eq!(bindings, before);
+
+    let mut devices = Devices::default();
+    devices.keys.press(KeyCode::Up);
+    assert_eq!(devices.resolve(&bindings).move_input, Vec2::Y);
+}
+
+#[test]
+fn mouse_looks_like_a_stick() {
+    let mut bindings = InputBindings::default();
+    let mut devices = Devices {
+        mouse_motion: Vec2::new(100.0, 50.0),
+        ..default()
+    };
+    let speed = bindings.mouse_sensitivity;
+    // Moving the mouse down looks down, like pulling the stick down.
+    assert_eq!(
+        devices.resolve(&bindings).look,
+        Vec2::new(100.0 * speed, -50.0 * speed)
+    );
+
+    bindings.invert_mouse_y = true;
+    assert_eq!(
+        devices.resolve(&bindings).look,
+        Vec2::new(100.0 * speed, 50.0 * speed)
+    );
+
+    // Slow mouse movement isn't drift, so the deadzone leaves it alone.
+    devices.mouse_motion = Vec2::new(bindings.look_deadzone / speed / 2.0, 0.0);
+    assert!(devices.resolve(&bindings).look.x > 0.0);
+}
+
+#[test]
+fn mouse_only_looks_during_mouse_look() {
+    let bindings = InputBindings::default();
+    let mut devices = Devices {
+        mouse_motion: Vec2::new(100.0, 50.0),
+        mouse_look: false,
+        ..default()
+    };
+    assert_eq!(devices.resolve(&bindings).look, Vec2::ZERO);
+
+    // Gamepad players don't share the mouse either.
+    devices.mouse_look = true;
+    devices.keyboard_mouse = false;
+    assert_eq!(devices.resolve(&bindings).look, Vec2::ZERO);
+}
+
+#[test]
+fn look_can_move_from_the_mouse_to_keys() {
+    let mut bindings = InputBindings::default();
+    let keys = Binding::KeyAxes {
+        up: KeyCode::I,
+        down: KeyCode::K,
+        left: KeyCode::J,
+        right: KeyCode::L,
+    };
+    assert!(bindings.rebind(Action::Look, keys));
+    assert!(!bindings
+        .bindings(Action::Look)
+        .contains(&Binding::MouseMotion));
+
+    let mut devices = Devices {
+        mouse_motion: Vec2::new(100.0, 50.0),
+        ..default()
+    };
+    devices.keys.press(KeyCode::L);
+    assert_eq!(devices.resolve(&bindings).look, Vec2::X);
+
+    assert!(bindings.rebind(Action::Look, Binding::MouseMotion));
+    assert!(!bindings.bindings(Action::Look).contains(&keys));
+}
+
+#[test]
+fn captures_the_pressed_button() {
+    let mut devices = Devices::with_gamepad();
+    assert_eq!(Binding::just_pressed(&devices.sources()), None);
+
+    let west = GamepadButton::new(Gamepad::new(0), GamepadButtonType::West);
+    devices.gamepad_buttons.press(west);
+    assert_eq!(
+        Binding::just_pressed(&devices.sources()),
+        Some(Binding::GamepadButton(GamepadButtonType::West))
+    );
+}
+
+#[test]
+fn bindings_round_trip_through_ron() {
+    let mut bindings = InputBindings::default();
+    assert!(bindings.rebind(Action::Jump, Binding::MouseButton(MouseButton::Right)));
+
+    let ron = bindings.to_ron().unwrap();
+    assert_eq!(InputBindings::from_ron(&ron).unwrap(), bindings);
+}
+
+#[test]
+fn bindings_saved_without_mouse_settings_still_load() {
+    let ron = "(actions: {}, move_deadzone: 0.3, look_deadzone: 0.1)";
+    let bindings = InputBindings::from_ron(ron).unwrap();
+    assert_eq!(
+        bindings.mouse_sensitivity,
+        InputBindings::default().mouse_sensitivity
+    );
+    assert!(!bindings.invert_mouse_y);
+}
+
+#[test]
+fn character_stops_while_rebinding() {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(Collider::cuboid(100.0, 0.1, 100.0), Transform::default());
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+    let ground_speed = |harness: &CharacterHarness| {
+        harness
+            .app()
+            .world
+            .get::<PlatformingCharacterPhysics>(harness.character())
+            .unwrap()
+            .ground_speed
+            .length()
+    };
+
+    // Drive the app's input directly, rather than the harness's scripted control.
+    harness
+        .app_mut()
+        .world
+        .resource_mut::<Input<KeyCode>>()
+        .press(KeyCode::Up);
+    for _ in 0..32 {
+        harness.app_mut().update();
+    }
+    assert!(ground_speed(&harness) > 1.0, "holding up didn't run");
+
+    // Up is still held, but it's no longer just pressed, so the rebind keeps waiting.
+    harness
+        .app_mut()
+        .insert_resource(PendingRebind(Action::Jump));
+    for _ in 0..128 {
+        harness.app_mut().update();
+    }
+    assert_eq!(ground_speed(&harness), 0.0);
+}
@@ -0,0 +1,197 @@
+//! Golden-trajectory regression tests for the character controller.
+//!
+//! Each scenario runs headless and compares the per-tick trajectory against a file in
+//! `tests/golden/`. After an intentional tuning change, regenerate them with
+//!
+//! ```sh
+//! UPDATE_GOLDEN=1 cargo test --test movement_golden
+//! ```
+//!
+//! and review the diff before committing.
+use std::{f32::consts::PI, fmt::Write, fs, path::PathBuf};
+
+use bevy::prelude::*;
+use bevy_walky::{
+    components::player::physics::AirSpeed,
+    harness::{CharacterHarness, ScriptedInput, TickSample},
+};
+use bevy_xpbd_3d::prelude::*;
+
+/// How far a value may drift from the golden file before the test fails.
+const TOLERANCE: f32 = 1e-3;
+
+fn flat_ground(harness: &mut CharacterHarness) {
+    harness.spawn_static_collider(
+        Collider::cuboid(100.0, 0.1, 100.0),
+        Transform::from_xyz(0.0, 0.0, 0.0),
+    );
+}
+
+/// Spawns a ramp rising along +X at `angle`, with its bottom edge at `start_x`.
+fn ramp(harness: &mut CharacterHarness, start_x: f32, angle: f32) {
+    let length = 20.0;
+    let rotation = Quat::from_rotation_z(angle);
+    let center = Vec3::new(start_x, 0.0, 0.0) + rotation.mul_vec3(Vec3::X * length * 0.5);
+    harness.spawn_static_collider(
+        Collider::cuboid(length, 0.1, 10.0),
+        Transform::from_translation(center).with_rotation(rotation),
+    );
+}
+
+#[test]
+fn run_on_flat_ground() {
+    let mut harness = CharacterHarness::new();
+    flat_ground(&mut harness);
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+
+    let trajectory = harness.run(180, |_| ScriptedInput::moving(Vec2::X));
+    assert_matches_golden("run_on_flat_ground", &trajectory);
+}
+
+#[test]
+fn jump() {
+    let mut harness = CharacterHarness::new();
+    flat_ground(&mut harness);
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+
+    let trajectory = harness.run(150, |tick| {
+        ScriptedInput::moving(Vec2::X).with_jump((30..40).contains(&tick))
+    });
+    assert_matches_golden("jump", &trajectory);
+}
+
+#[test]
+fn run_off_ledge() {
+    let mut harness = CharacterHarness::new();
+    flat_ground(&mut harness);
+    // A raised platform that ends at x = 5.
+    harness.spawn_static_collider(
+        Collider::cuboid(20.0, 2.0, 10.0),
+        Transform::from_xyz(-5.0, 1.0, 0.0),
+    );
+    harness.spawn_character(Transform::from_xyz(-10.0, 2.5, 0.0));
+
+    let trajectory = harness.run(180, |_| ScriptedInput::moving(Vec2::X));
+    assert_matches_golden("run_off_ledge", &trajectory);
+}
+
+#[test]
+fn wall_run_threshold() {
+    let mut harness = CharacterHarness::new();
+    flat_ground(&mut harness);
+    // Just past 45 degrees, where the character can only stay on the surface while it's fast
+    // enough.
+    ramp(&mut harness, 8.0, PI / 4.0 + 0.1);
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+
+    let trajectory = harness.run(240, |_| ScriptedInput::moving(Vec2::X));
+    assert_matches_golden("wall_run_threshold", &trajectory);
+}
+
+#[test]
+fn quarter_pipe_into_ceiling() {
+    let mut harness = CharacterHarness::new();
+    // The ground and the ceiling line up with the ends of the curve.
+    harness.spawn_static_collider(
+        Collider::cuboid(20.0, 0.1, 10.0),
+        Transform::from_xyz(-10.0, -0.05, 0.0),
+    );
+    harness.spawn_arc(Vec3::new(0.0, 4.0, 0.0), 4.0, 0.0..PI, 16);
+    harness.spawn_static_collider(
+        Collider::cuboid(20.0, 0.1, 10.0),
+        Transform::from_xyz(-10.0, 8.05, 0.0),
+    );
+    harness.spawn_character(Transform::from_xyz(-15.0, 0.5, 0.0));
+
+    let trajectory = harness.run(240, |_| ScriptedInput::moving(Vec2::X));
+    assert_matches_golden("quarter_pipe_into_ceiling", &trajectory);
+}
+
+#[test]
+fn land_on_slope() {
+    let mut harness = CharacterHarness::new();
+    ramp(&mut harness, -10.0, PI / 9.0);
+    harness.spawn_character(Transform::from_xyz(0.0, 8.0, 0.0));
+
+    let trajectory = harness.run(180, |_| ScriptedInput::moving(Vec2::X));
+    assert_matches_golden("land_on_slope", &trajectory);
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{name}.txt"))
+}
+
+/// One line per tick: `tick px py pz vx vy vz state value`. The value is the slope angle on the
+/// ground, and the vertical air speed in the air.
+fn format_trajectory(trajectory: &[TickSample]) -> String {
+    let mut out = String::new();
+    for sample in trajectory {
+        let (state, value) = match sample.air_speed {
+            AirSpeed::Grounded { angle, .. } => ("grounded", angle),
+            AirSpeed::InAir(velocity) => ("in_air", velocity.y),
+        };
+        writeln!(
+            out,
+            "{} {:.6} {:.6} {:.6} {:.6} {:.6} {:.6} {} {:.6}",
+            sample.tick,
+            sample.position.x,
+            sample.position.y,
+            sample.position.z,
+            sample.velocity.x,
+            sample.velocity.y,
+            sample.velocity.z,
+            state,
+            value
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn assert_matches_golden(name: &str, trajectory: &[TickSample]) {
+    let path = golden_path(name);
+    let actual = format_trajectory(trajectory);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let Ok(expected) = fs::read_to_string(&path) else {
+        panic!(
+            "{} does not exist. Record it with UPDATE_GOLDEN=1, then review it and commit it.",
+            path.display()
+        );
+    };
+
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let actual_lines: Vec<&str> = actual.lines().collect();
+    assert_eq!(
+        expected_lines.len(),
+        actual_lines.len(),
+        "{name}: tick count differs from golden file"
+    );
+
+    for (expected_line, actual_line) in expected_lines.iter().zip(actual_lines.iter()) {
+        let expected_fields: Vec<&str> = expected_line.split_whitespace().collect();
+        let actual_fields: Vec<&str> = actual_line.split_whitespace().collect();
+        assert_eq!(
+            expected_fields.len(),
+            actual_fields.len(),
+            "{name}: malformed line\n  expected: {expected_line}\n  actual:   {actual_line}"
+        );
+        for (expected_field, actual_field) in expected_fields.iter().zip(actual_fields.iter()) {
+            let matches = match (expected_field.parse::<f32>(), actual_field.parse::<f32>()) {
+                (Ok(e), Ok(a)) => (e - a).abs() <= TOLERANCE,
+                _ => expected_field == actual_field,
+            };
+            assert!(
+                matches,
+                "{name}: trajectory diverged from golden file\n  expected: {expected_line}\n  actual:   {actual_line}"
+            );
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Movement checks that are easier to state as properties of a run than as golden trajectories.
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use bevy::prelude::*;
+use bevy_walky::{
+    components::player::physics::{AirSpeed, PlatformingCharacterValues},
+    harness::{CharacterHarness, ScriptedInput, TickSample},
+};
+use bevy_xpbd_3d::prelude::*;
+
+fn flat_ground(harness: &mut CharacterHarness) {
+    harness.spawn_static_collider(
+        Collider::cuboid(100.0, 0.1, 100.0),
+        Transform::from_xyz(0.0, 0.0, 0.0),
+    );
+}
+
+/// Spawns a ramp along +X at `angle`, with its near edge at `start_x`. Negative angles go
+/// downhill.
+fn ramp(harness: &mut CharacterHarness, start_x: f32, angle: f32, length: f32) {
+    let rotation = Quat::from_rotation_z(angle);
+    let center = Vec3::new(start_x, 0.0, 0.0) + rotation.mul_vec3(Vec3::X * length * 0.5);
+    harness.spawn_static_collider(
+        Collider::cuboid(length, 0.1, 10.0),
+        Transform::from_translation(center).with_rotation(rotation),
+    );
+}
+
+fn values(harness: &CharacterHarness) -> &PlatformingCharacterValues {
+    harness
+        .app()
+        .world
+        .get::<PlatformingCharacterValues>(harness.character())
+        .unwrap()
+}
+
+fn grounded(sample: &TickSample) -> bool {
+    matches!(sample.air_speed, AirSpeed::Grounded { .. })
+}
+
+fn speed(sample: &TickSample) -> f32 {
+    sample.velocity.length()
+}
+
+/// How far around a curve spawned by `CharacterHarness::spawn_arc` the character is, measured
+/// from straight below `center` like the arc's angles.
+fn arc_angle(sample: &TickSample, center: Vec3) -> f32 {
+    let offset = sample.position - center;
+    offset.x.atan2(-offset.y)
+}
+
+#[test]
+fn slopes_slow_running_uphill_and_speed_it_up_downhill() {
+    let mut uphill = CharacterHarness::new();
+    flat_ground(&mut uphill);
+    ramp(&mut uphill, 5.0, PI / 9.0, 60.0);
+    uphill.spawn_character(Transform::from_xyz(-10.0, 0.5, 0.0));
+    let top_speed = values(&uphill).top_speed;
+
+    let trajectory = uphill.run(150, |_| ScriptedInput::moving(Vec2::X));
+    let on_flat: Vec<_> = trajectory
+        .iter()
+        .filter(|s| (0.0..4.0).contains(&s.position.x))
+        .collect();
+    let on_ramp: Vec<_> = trajectory
+        .iter()
+        .filter(|s| s.position.x > 8.0 && grounded(s))
+        .collect();
+    assert!(!on_flat.is_empty() && !on_ramp.is_empty());
+    for sample in on_flat {
+        assert!((speed(sample) - top_speed).abs() < 0.01, "{sample:?}");
+    }
+    for sample in on_ramp {
+        assert!(speed(sample) < top_speed - 0.05, "{sample:?}");
+    }
+
+    let mut downhill = CharacterHarness::new();
+    ramp(&mut downhill, 0.0, -PI / 9.0, 60.0);
+    downhill.spawn_character(Transform::from_xyz(1.0, 0.5, 0.0));
+
+    let trajectory = downhill.run(150, |_| ScriptedInput::moving(Vec2::X));
+    let last = trajectory.last().unwrap();
+    assert!(grounded(last), "{last:?}");
+    // Input stops accelerating at top speed, but the slope keeps going.
+    assert!(speed(last) > top_speed + 2.0, "{last:?}");
+}
+
+#[test]
+fn falls_off_a_wall_below_min_stick_speed() {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(
+        Collider::cuboid(40.0, 0.1, 10.0),
+        Transform::from_xyz(-20.0, -0.05, 0.0),
+    );
+    // A quarter pipe that turns into a wall.
+    let center = Vec3::new(0.0, 4.0, 0.0);
+    harness.spawn_arc(center, 4.0, 0.0..FRAC_PI_2, 8);
+    harness.spawn_static_collider(
+        Collider::cuboid(0.1, 20.0, 10.0),
+        Transform::from_xyz(4.05, 14.0, 0.0),
+    );
+    harness.spawn_character(Transform::from_xyz(-15.0, 0.5, 0.0));
+    let min_stick_speed = values(&harness).min_stick_speed;
+
+    // Run up the curve, then let go once it's steep, so friction and the slope slow the
+    // character down.
+    let mut let_go = false;
+    let mut fell_off = None;
+    for _ in 0..240 {
+        let input = if let_go {
+            ScriptedInput::default()
+        } else {
+            ScriptedInput::moving(Vec2::X)
+        };
+        let sample = harness.tick(input);
+        let angle = arc_angle(&sample, center);
+        let_go |= sample.position.x > 0.0 && angle > PI / 3.0;
+        if let_go && !grounded(&sample) {
+            fell_off = Some((sample, angle));
+            break;
+        }
+    }
+
+    let (sample, angle) = fell_off.expect("the character never fell off the wall");
+    assert!(angle > FRAC_PI_4, "fell off below 45 degrees: {sample:?}");
+    assert!(speed(&sample) < min_stick_speed, "{sample:?}");
+}
+
+/// A full loop, with the character starting inside it at the bottom.
+fn inside_a_loop() -> (CharacterHarness, Vec3) {
+    let mut harness = CharacterHarness::new();
+    let center = Vec3::new(0.0, 4.0, 0.0);
+    harness.spawn_arc(center, 4.0, 0.0..2.0 * PI, 32);
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+    (harness, center)
+}
+
+#[test]
+fn runs_all_the_way_around_a_loop() {
+    let (mut harness, center) = inside_a_loop();
+    let trajectory = harness.run(192, |_| ScriptedInput::moving(Vec2::X));
+
+    let landed = trajectory
+        .iter()
+        .position(grounded)
+        .expect("never landed in the loop");
+    let mut travelled = 0.0;
+    let mut last_angle = arc_angle(&trajectory[landed], center);
+    for sample in &trajectory[landed..] {
+        assert!(grounded(sample), "came off the loop: {sample:?}");
+        let angle = arc_angle(sample, center);
+        // Unwrap across the top of the loop, where the angle jumps from PI to -PI.
+        let mut step = angle - last_angle;
+        if step < -PI {
+            step += 2.0 * PI;
+        }
+        travelled += step;
+        last_angle = angle;
+    }
+    assert!(travelled > 2.0 * PI, "only got {travelled} radians around");
+}
+
+#[test]
+fn detaches_from_a_loop_when_too_slow() {
+    let (mut harness, center) = inside_a_loop();
+
+    // Run until 30 degrees up, then coast, which isn't enough to get over the top.
+    let mut let_go = false;
+    let mut highest = f32::MIN;
+    let mut detached = false;
+    let mut landed_again = false;
+    for _ in 0..240 {
+        let input = if let_go {
+            ScriptedInput::default()
+        } else {
+            ScriptedInput::moving(Vec2::X)
+        };
+        let sample = harness.tick(input);
+        let angle = arc_angle(&sample, center);
+        highest = highest.max(sample.position.y);
+        let_go |= sample.position.x > 0.0 && angle > PI / 6.0;
+        if let_go && !grounded(&sample) {
+            detached = true;
+        }
+        if detached && grounded(&sample) {
+            landed_again = true;
+            break;
+        }
+    }
+
+    assert!(detached, "never came off the loop");
+    assert!(
+        highest < center.y,
+        "made it to the top half of the loop, {highest} high"
+    );
+    assert!(landed_again, "never landed back in the loop");
+}
+
+/// How high the character gets above where it was standing, jumping on tick 10 and holding
+/// jump for `held` ticks.
+fn jump_height(held: u32) -> f32 {
+    let mut harness = CharacterHarness::new();
+    flat_ground(&mut harness);
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+
+    let standing = harness.run(10, |_| ScriptedInput::default());
+    let ground = standing.last().unwrap().position.y;
+    let trajectory = harness.run(120, |tick| {
+        ScriptedInput::default().with_jump((10..10 + held).contains(&tick))
+    });
+    trajectory
+        .iter()
+        .map(|sample| sample.position.y)
+        .fold(ground, f32::max)
+        - ground
+}
+
+#[test]
+fn tapping_jump_is_a_short_hop() {
+    let full = jump_height(60);
+    let short = jump_height(1);
+    assert!(full > 2.0, "full jump only got {full} high");
+    assert!(short > 0.0, "short hop didn't leave the ground");
+    assert!(
+        short < full * 0.5,
+        "short hop got {short} high, against {full} for a full jump"
+    );
+}
+
+#[test]
+fn sideways_gravity_makes_a_wall_the_floor() {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(
+        Collider::cuboid(0.1, 100.0, 100.0),
+        Transform::from_xyz(0.0, 0.0, 0.0),
+    );
+    let character = harness.spawn_character(Transform::from_xyz(1.0, 0.0, 0.0));
+    harness
+        .app_mut()
+        .world
+        .get_mut::<PlatformingCharacterValues>(character)
+        .unwrap()
+        .gravity = Vec3::new(-12.8, 0.0, 0.0);
+
+    let falling = harness.run(60, |_| ScriptedInput::default());
+    let landed = falling.last().unwrap();
+    assert!(grounded(landed), "never landed on the wall: {landed:?}");
+
+    let running = harness.run(120, |_| ScriptedInput::moving(Vec2::X));
+    let last = running.last().unwrap();
+    assert!(grounded(last), "{last:?}");
+    assert!(
+        (last.position.x - landed.position.x).abs() < 0.1,
+        "moved off the wall from {landed:?} to {last:?}"
+    );
+    let along_wall = (last.position - landed.position).length();
+    assert!(along_wall > 5.0, "only ran {along_wall} along the wall");
+}
+
+/// Runs off the end of a raised platform, pressing jump for one tick `jump_after` ticks after
+/// leaving it. Returns the ticks from leaving the ledge on.
+fn run_off_ledge_and_jump(jump_after: Option<usize>) -> Vec<TickSample> {
+    let mut harness = CharacterHarness::new();
+    flat_ground(&mut harness);
+    // A raised platform that ends at x = 5.
+    harness.spawn_static_collider(
+        Collider::cuboid(20.0, 2.0, 10.0),
+        Transform::from_xyz(-5.0, 1.0, 0.0),
+    );
+    harness.spawn_character(Transform::from_xyz(-10.0, 2.5, 0.0));
+
+    let mut on_platform = false;
+    loop {
+        let sample = harness.tick(ScriptedInput::moving(Vec2::X));
+        on_platform |= grounded(&sample);
+        if on_platform && !grounded(&sample) {
+            break;
+        }
+        assert!(sample.tick < 300, "never ran off the ledge");
+    }
+    (1..40)
+        .map(|ticks_off_ledge| {
+            let jump = jump_after == Some(ticks_off_ledge);
+            harness.tick(ScriptedInput::moving(Vec2::X).with_jump(jump))
+        })
+        .collect()
+}
+
+#[test]
+fn jumping_just_after_running_off_a_ledge_still_jumps() {
+    let jumped = |trajectory: &[TickSample]| trajectory.iter().any(|s| s.velocity.y > 5.0);
+
+    // Two ticks is well inside the coyote time.
+    assert!(jumped(&run_off_ledge_and_jump(Some(2))));
+    // Twelve ticks is well past it.
+    assert!(!jumped(&run_off_ledge_and_jump(Some(12))));
+    assert!(!jumped(&run_off_ledge_and_jump(None)));
+}
+
+/// Runs up a quarter pipe and along the ceiling it leads into, which is too flat to hold on to.
+/// Once the character drops off, presses jump for one tick two ticks later if `jump` is set.
+/// Returns the ticks from dropping off on.
+fn fall_off_ceiling_and_jump(jump: bool) -> Vec<TickSample> {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(
+        Collider::cuboid(20.0, 0.1, 10.0),
+        Transform::from_xyz(-10.0, -0.05, 0.0),
+    );
+    harness.spawn_arc(Vec3::new(0.0, 4.0, 0.0), 4.0, 0.0..PI, 16);
+    harness.spawn_static_collider(
+        Collider::cuboid(20.0, 0.1, 10.0),
+        Transform::from_xyz(-10.0, 8.05, 0.0),
+    );
+    harness.spawn_character(Transform::from_xyz(-15.0, 0.5, 0.0));
+
+    let mut on_ceiling = false;
+    loop {
+        let sample = harness.tick(ScriptedInput::moving(Vec2::X));
+        on_ceiling |= grounded(&sample) && sample.position.y > 7.0;
+        if on_ceiling && !grounded(&sample) {
+            break;
+        }
+        assert!(sample.tick < 300, "never dropped off the ceiling");
+    }
+    (1..20)
+        .map(|ticks_off_ceiling| {
+            harness.tick(ScriptedInput::moving(Vec2::X).with_jump(jump && ticks_off_ceiling == 2))
+        })
+        .collect()
+}
+
+#[test]
+fn falling_off_a_ceiling_doesnt_give_coyote_time() {
+    let without_jump = fall_off_ceiling_and_jump(false);
+    let with_jump = fall_off_ceiling_and_jump(true);
+    assert_eq!(
+        without_jump, with_jump,
+        "jumped after dropping off the ceiling"
+    );
+}
+
+/// Drops the character onto flat ground, pressing jump for one tick `early` ticks before it
+/// lands. Returns the landing tick and a few after it.
+fn land_with_jump_pressed_early(early: Option<usize>) -> Vec<TickSample> {
+    let drop = || {
+        let mut harness = CharacterHarness::new();
+        flat_ground(&mut harness);
+        harness.spawn_character(Transform::from_xyz(0.0, 3.0, 0.0));
+        harness
+    };
+    let landing = drop()
+        .run(120, |_| ScriptedInput::default())
+        .iter()
+        .position(grounded)
+        .expect("never landed");
+
+    let mut harness = drop();
+    let trajectory = harness.run(landing as u32 + 10, |tick| {
+        let press = early.map(|early| landing - early);
+        ScriptedInput::default().with_jump(press == Some(tick as usize))
+    });
+    trajectory[landing..].to_vec()
+}
+
+#[test]
+fn pressing_jump_just_before_landing_jumps_on_touchdown() {
+    let jumped = |trajectory: &[TickSample]| trajectory.iter().any(|s| s.velocity.y > 5.0);
+
+    // Three ticks is inside the jump buffer.
+    assert!(jumped(&land_with_jump_pressed_early(Some(3))));
+    // Twelve ticks is long enough that the press is forgotten.
+    assert!(!jumped(&land_with_jump_pressed_early(Some(12))));
+    assert!(!jumped(&land_with_jump_pressed_early(None)));
+}
+
+#[test]
+fn frame_rate_doesnt_change_the_run() {
+    // Runs two seconds up a ramp with a jump in the middle, drawing `frame_hz` frames a second.
+    let run_at = |frame_hz: u32| {
+        let mut harness = CharacterHarness::with_frame_rate(frame_hz as f64);
+        flat_ground(&mut harness);
+        ramp(&mut harness, 5.0, PI / 9.0, 60.0);
+        harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+        let trajectory = harness.run(2 * frame_hz, |frame| {
+            let seconds = frame as f32 / frame_hz as f32;
+            ScriptedInput::moving(Vec2::X).with_jump((0.5..0.75).contains(&seconds))
+        });
+        *trajectory.last().unwrap()
+    };
+
+    // Both frame rates fit a whole number of fixed ticks into the jump and the run.
+    let slow = run_at(32);
+    let fast = run_at(128);
+    assert!(slow.position.x > 10.0, "didn't get anywhere: {slow:?}");
+    assert!(
+        slow.position.distance(fast.position) < 1e-3,
+        "32 fps ended at {:?}, 128 fps at {:?}",
+        slow.position,
+        fast.position
+    );
+}
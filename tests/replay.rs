@@ -0,0 +1,147 @@
+//! Input recordings have to reproduce a run exactly, or they're useless as bug reports.
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_walky::{
+    components::{
+        input::PlayerSlot,
+        player::{physics::PlatformingCharacterControl, replay::InputRecording},
+    },
+    harness::{CharacterHarness, ScriptedInput, TickSample},
+    systems::player::spawn::platforming_character_bundle,
+};
+use bevy_xpbd_3d::prelude::*;
+
+fn course() -> CharacterHarness {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(Collider::cuboid(100.0, 0.1, 100.0), Transform::default());
+    harness.spawn_static_collider(
+        Collider::cuboid(20.0, 0.1, 10.0),
+        Transform::from_xyz(15.0, 3.0, 0.0).with_rotation(Quat::from_rotation_z(PI / 6.0)),
+    );
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+    harness
+}
+
+fn scripted_run(harness: &mut CharacterHarness) -> Vec<TickSample> {
+    harness.run(200, |tick| {
+        let angle = tick as f32 * 0.01;
+        ScriptedInput::moving(Vec2::new(angle.cos(), angle.sin()))
+            .with_jump((60..75).contains(&tick) || (140..145).contains(&tick))
+    })
+}
+
+#[test]
+fn replay_reproduces_recorded_run() {
+    let mut original = course();
+    original.start_recording();
+    let expected = scripted_run(&mut original);
+    let recording = original.take_recording().unwrap();
+    assert_eq!(recording.ticks.len(), expected.len());
+
+    let mut bytes = Vec::new();
+    recording.write_to(&mut bytes).unwrap();
+    let recording = InputRecording::read_from(bytes.as_slice()).unwrap();
+
+    let mut replayed = course();
+    let actual = replayed.play(recording);
+    assert_eq!(expected, actual);
+}
+
+/// Two players on flat ground, side by side. The second joined after the first.
+fn two_player_course() -> (CharacterHarness, Entity, Entity) {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(Collider::cuboid(100.0, 0.1, 100.0), Transform::default());
+    let first = harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+    harness
+        .app_mut()
+        .world
+        .entity_mut(first)
+        .insert(PlayerSlot::keyboard_mouse());
+    let second = harness
+        .app_mut()
+        .world
+        .spawn((
+            platforming_character_bundle(Transform::from_xyz(0.0, 0.5, 10.0)),
+            PlayerSlot {
+                join_order: 1,
+                ..PlayerSlot::gamepad(Gamepad::new(0))
+            },
+        ))
+        .id();
+    (harness, first, second)
+}
+
+fn position(harness: &CharacterHarness, character: Entity) -> Vec3 {
+    harness.app().world.get::<Position>(character).unwrap().0
+}
+
+#[test]
+fn replay_gives_each_player_their_own_inputs() {
+    let (mut original, first, second) = two_player_course();
+    original.start_recording();
+    for _ in 0..60 {
+        original
+            .app_mut()
+            .world
+            .get_mut::<PlatformingCharacterControl>(second)
+            .unwrap()
+            .move_input = Vec2::NEG_X;
+        original.tick(ScriptedInput::moving(Vec2::X));
+    }
+    let recording = original.take_recording().unwrap();
+    assert!(recording.ticks.iter().all(|tick| tick.players.len() == 2));
+
+    let (mut replayed, replayed_first, replayed_second) = two_player_course();
+    replayed.play(recording);
+    // The players ran in opposite directions, and the replay keeps them apart.
+    assert!(position(&replayed, replayed_first).x > 5.0);
+    assert!(position(&replayed, replayed_second).x < -5.0);
+    assert_eq!(
+        position(&original, first),
+        position(&replayed, replayed_first)
+    );
+    assert_eq!(
+        position(&original, second),
+        position(&replayed, replayed_second)
+    );
+}
+
+#[test]
+fn recording_round_trips() {
+    let mut harness = course();
+    harness.start_recording();
+    scripted_run(&mut harness);
+    let recording = harness.take_recording().unwrap();
+
+    let mut bytes = Vec::new();
+    recording.write_to(&mut bytes).unwrap();
+    // One player, so a count byte and one player's input per tick.
+    assert_eq!(bytes.len(), 9 + 22 * recording.ticks.len());
+    assert_eq!(
+        InputRecording::read_from(bytes.as_slice()).unwrap(),
+        recording
+    );
+}
+
+#[test]
+fn rejects_other_files() {
+    assert!(InputRecording::read_from(&b"not a recording"[..]).is_err());
+}
+
+#[test]
+fn rejects_truncated_recordings() {
+    // Claims to hold four billion ticks, but ends after the header.
+    let mut bytes = b"WLKY".to_vec();
+    bytes.push(3);
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    assert!(InputRecording::read_from(bytes.as_slice()).is_err());
+}
+
+#[test]
+fn rejects_older_versions() {
+    let mut bytes = Vec::new();
+    InputRecording::default().write_to(&mut bytes).unwrap();
+    bytes[4] = 2;
+    assert!(InputRecording::read_from(bytes.as_slice()).is_err());
+}
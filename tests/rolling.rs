@@ -0,0 +1,154 @@
+//! Crouching while running curls the character into a roll, which only brakes against the input
+//! and shrinks the character's collider until it stands back up.
+use bevy::prelude::*;
+use bevy_walky::{
+    components::player::physics::{PlatformingCharacterPhysics, PlatformingCharacterValues},
+    harness::{CharacterHarness, ScriptedInput},
+};
+use bevy_xpbd_3d::prelude::*;
+
+/// Flat ground to roll along.
+fn flat_ground() -> CharacterHarness {
+    let mut harness = CharacterHarness::new();
+    harness.spawn_static_collider(Collider::cuboid(100.0, 0.1, 100.0), Transform::default());
+    harness
+}
+
+fn spawn_standing(harness: &mut CharacterHarness) {
+    harness.spawn_character(Transform::from_xyz(0.0, 0.5, 0.0));
+    harness.run(10, |_| ScriptedInput::default());
+}
+
+/// Runs along +X at top speed, then crouches into a roll and lets go of the stick.
+fn roll_at_speed(harness: &mut CharacterHarness) {
+    harness.run(40, |_| ScriptedInput::moving(Vec2::X));
+    harness.tick(ScriptedInput::moving(Vec2::X).with_crouch(true));
+    harness.tick(ScriptedInput::default());
+}
+
+fn physics(harness: &CharacterHarness) -> &PlatformingCharacterPhysics {
+    harness
+        .app()
+        .world
+        .get::<PlatformingCharacterPhysics>(harness.character())
+        .unwrap()
+}
+
+fn values(harness: &CharacterHarness) -> &PlatformingCharacterValues {
+    harness
+        .app()
+        .world
+        .get::<PlatformingCharacterValues>(harness.character())
+        .unwrap()
+}
+
+fn collider_radius(harness: &CharacterHarness) -> f32 {
+    harness
+        .app()
+        .world
+        .get::<Collider>(harness.character())
+        .unwrap()
+        .shape()
+        .as_ball()
+        .expect("the character's collider should be a ball")
+        .radius
+}
+
+#[test]
+fn crouching_while_running_starts_a_roll() {
+    let mut harness = flat_ground();
+    spawn_standing(&mut harness);
+    roll_at_speed(&mut harness);
+    assert!(physics(&harness).rolling);
+    assert_eq!(collider_radius(&harness), values(&harness).rolling_radius);
+}
+
+#[test]
+fn crouching_while_standing_still_doesnt_roll() {
+    let mut harness = flat_ground();
+    spawn_standing(&mut harness);
+    harness.tick(ScriptedInput::default().with_crouch(true));
+    harness.tick(ScriptedInput::default());
+    assert!(!physics(&harness).rolling);
+    let (_, standing_radius) = values(&harness).body_radii(false);
+    assert_eq!(collider_radius(&harness), standing_radius);
+}
+
+#[test]
+fn roll_stops_once_it_slows_down() {
+    let mut harness = flat_ground();
+    spawn_standing(&mut harness);
+    roll_at_speed(&mut harness);
+    // Friction alone brings a top speed roll to a stop in well under 3 seconds.
+    harness.run(3 * 64, |_| ScriptedInput::default());
+    assert!(!physics(&harness).rolling);
+    assert!(physics(&harness).ground_speed.length() < values(&harness).roll_stop_speed);
+    let (_, standing_radius) = values(&harness).body_radii(false);
+    assert_eq!(collider_radius(&harness), standing_radius);
+}
+
+#[test]
+fn holding_forward_doesnt_speed_up_a_roll() {
+    let mut harness = flat_ground();
+    spawn_standing(&mut harness);
+    roll_at_speed(&mut harness);
+    let speed = physics(&harness).ground_speed.length();
+    harness.run(16, |_| ScriptedInput::moving(Vec2::X));
+    assert!(physics(&harness).rolling);
+    assert!(physics(&harness).ground_speed.length() < speed);
+}
+
+#[test]
+fn holding_back_brakes_a_roll() {
+    let coast = |input: ScriptedInput| {
+        let mut harness = flat_ground();
+        spawn_standing(&mut harness);
+        roll_at_speed(&mut harness);
+        harness.run(16, |_| input);
+        assert!(physics(&harness).rolling);
+        physics(&harness).ground_speed.length()
+    };
+    let coasting = coast(ScriptedInput::default());
+    let braking = coast(ScriptedInput::moving(Vec2::NEG_X));
+    let deceleration = {
+        let mut harness = flat_ground();
+        spawn_standing(&mut harness);
+        values(&harness).roll_deceleration_speed
+    };
+    // A quarter second of braking takes off an extra quarter of the deceleration speed.
+    let expected = coasting - deceleration * 0.25;
+    assert!(
+        (braking - expected).abs() < 0.05,
+        "braked to {braking}, expected {expected}"
+    );
+}
+
+#[test]
+fn keeps_rolling_under_a_low_ceiling() {
+    let mut harness = flat_ground();
+    // Low enough that the rolling ball fits under it but the standing one doesn't.
+    let ceiling_length = 16.0;
+    harness.spawn_static_collider(
+        Collider::cuboid(ceiling_length, 1.0, 4.0),
+        Transform::from_xyz(4.0 + ceiling_length / 2.0, 1.3, 0.0),
+    );
+    spawn_standing(&mut harness);
+    roll_at_speed(&mut harness);
+    harness.run(3 * 64, |_| ScriptedInput::default());
+    let stuck_at = harness.sample().position.x;
+    assert!(
+        (4.0..4.0 + ceiling_length).contains(&stuck_at),
+        "the roll didn't stop under the ceiling, but at {stuck_at}"
+    );
+    assert!(physics(&harness).rolling, "stood up into the ceiling");
+    assert_eq!(collider_radius(&harness), values(&harness).rolling_radius);
+
+    // Holding forward nudges the character out, and it stands up once it's clear.
+    harness.run(10 * 64, |_| ScriptedInput::moving(Vec2::X));
+    let out_at = harness.sample().position.x;
+    assert!(out_at > 4.0 + ceiling_length, "only got out to {out_at}");
+    harness.run(3 * 64, |_| ScriptedInput::default());
+    assert!(!physics(&harness).rolling);
+    let (_, standing_radius) = values(&harness).body_radii(false);
+    assert_eq!(collider_radius(&harness), standing_radius);
+}
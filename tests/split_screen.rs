@@ -0,0 +1,109 @@
+//! Split-screen layouts are plain rectangles, so they're checked without a renderer.
+use bevy::prelude::*;
+use bevy_walky::{
+    components::camera::SplitScreen,
+    systems::world::camera::{split_screen_layout, ViewportRect},
+};
+
+const WINDOW: UVec2 = UVec2::new(1280, 720);
+
+fn rect(x: u32, y: u32, width: u32, height: u32) -> ViewportRect {
+    ViewportRect {
+        position: UVec2::new(x, y),
+        size: UVec2::new(width, height),
+    }
+}
+
+fn overlaps(a: &ViewportRect, b: &ViewportRect) -> bool {
+    let (a_max, b_max) = (a.position + a.size, b.position + b.size);
+    a.position.x < b_max.x
+        && b.position.x < a_max.x
+        && a.position.y < b_max.y
+        && b.position.y < a_max.y
+}
+
+#[test]
+fn no_players_get_no_viewports() {
+    assert!(split_screen_layout(0, WINDOW, SplitScreen::Horizontal).is_empty());
+}
+
+#[test]
+fn one_player_fills_the_window() {
+    assert_eq!(
+        split_screen_layout(1, WINDOW, SplitScreen::Horizontal),
+        vec![rect(0, 0, 1280, 720)]
+    );
+}
+
+#[test]
+fn two_players_split_along_the_chosen_direction() {
+    assert_eq!(
+        split_screen_layout(2, WINDOW, SplitScreen::Horizontal),
+        vec![rect(0, 0, 1280, 360), rect(0, 360, 1280, 360)]
+    );
+    assert_eq!(
+        split_screen_layout(2, WINDOW, SplitScreen::Vertical),
+        vec![rect(0, 0, 640, 720), rect(640, 0, 640, 720)]
+    );
+}
+
+#[test]
+fn three_players_split_the_second_half_again() {
+    assert_eq!(
+        split_screen_layout(3, WINDOW, SplitScreen::Horizontal),
+        vec![
+            rect(0, 0, 1280, 360),
+            rect(0, 360, 640, 360),
+            rect(640, 360, 640, 360)
+        ]
+    );
+    assert_eq!(
+        split_screen_layout(3, WINDOW, SplitScreen::Vertical),
+        vec![
+            rect(0, 0, 640, 720),
+            rect(640, 0, 640, 360),
+            rect(640, 360, 640, 360)
+        ]
+    );
+}
+
+#[test]
+fn four_players_get_quadrants() {
+    assert_eq!(
+        split_screen_layout(4, WINDOW, SplitScreen::Horizontal),
+        vec![
+            rect(0, 0, 640, 360),
+            rect(640, 0, 640, 360),
+            rect(0, 360, 640, 360),
+            rect(640, 360, 640, 360)
+        ]
+    );
+}
+
+#[test]
+fn odd_window_sizes_are_covered_without_gaps_or_overlaps() {
+    let window = UVec2::new(1279, 719);
+    for split in [SplitScreen::Horizontal, SplitScreen::Vertical] {
+        for players in 1..=4 {
+            let layout = split_screen_layout(players, window, split);
+            assert_eq!(layout.len(), players);
+
+            let area: u32 = layout.iter().map(|r| r.size.x * r.size.y).sum();
+            assert_eq!(area, window.x * window.y, "{players} players, {split:?}");
+            for (i, a) in layout.iter().enumerate() {
+                assert!((a.position + a.size).cmple(window).all());
+                for b in &layout[i + 1..] {
+                    assert!(!overlaps(a, b), "{a:?} overlaps {b:?}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn more_players_fit_in_a_grid() {
+    let layout = split_screen_layout(5, WINDOW, SplitScreen::Horizontal);
+    assert_eq!(layout.len(), 5);
+    assert_eq!(layout[0], rect(0, 0, 426, 360));
+    assert_eq!(layout[4], rect(426, 360, 427, 360));
+}